@@ -1,10 +1,12 @@
+use std::error::Error;
+use std::fmt;
 use std::ops::RangeInclusive;
 use std::str::FromStr;
 
 type Position = (usize, usize);
 
-#[derive(Copy, Clone)]
-enum Action {
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Action {
     Off,
     On,
     Toggle
@@ -23,13 +25,96 @@ impl FromStr for Action {
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
 pub struct Instruction {
     pos1: Position,
     pos2: Position,
     action: Action
 }
 
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseError {
+    pub line: usize,
+    pub column: usize,
+    pub message: String,
+}
+
+impl fmt::Display for ParseError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}: {}", self.line, self.column, self.message)
+    }
+}
+
+impl Error for ParseError {}
+
+// Words of a line together with their (0-based) column
+fn words(line: &str) -> Vec<(usize, &str)> {
+    let mut words = vec![];
+    let mut start = None;
+    for (idx, ch) in line.char_indices().chain([(line.len(), ' ')]) {
+        match (ch.is_whitespace(), start) {
+            (true, Some(begin)) => {
+                words.push((begin, &line[begin..idx]));
+                start = None;
+            },
+            (false, None) => start = Some(idx),
+            _ => {}
+        }
+    }
+    words
+}
+
+fn error<T>(column: usize, message: &str) -> Result<T, ParseError> {
+    Err(ParseError { line: 1, column: column + 1, message: message.to_string() })
+}
+
+fn position(word: Option<&(usize, &str)>, end: usize) -> Result<Position, ParseError> {
+    let &(column, word) = word.map_or_else(|| error(end, "Expected a coordinate"), Ok)?;
+    let Some((x, y)) = word.split_once(',') else {
+        return error(column, "Expected a coordinate like 0,0");
+    };
+    // Rectangles are stored with an exclusive end, so the largest usize is not a valid coordinate
+    let x = x.parse().ok().filter(|&x| x < usize::MAX)
+        .map_or_else(|| error(column, "Invalid x coordinate"), Ok)?;
+    let y = y.parse().ok().filter(|&y| y < usize::MAX)
+        .map_or_else(|| error(column + word.find(',').unwrap() + 1, "Invalid y coordinate"), Ok)?;
+    Ok((x, y))
+}
+
+impl FromStr for Instruction {
+    type Err = ParseError;
+
+    // Errors are reported on line 1, the generator fills in the actual line
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words = words(line);
+        let (action, rest) = match words.as_slice() {
+            [(_, "turn"), (column, keyword), rest @ ..] => match keyword.parse() {
+                Ok(action @ (Action::On | Action::Off)) => (action, rest),
+                _ => return error(*column, "Expected on or off"),
+            },
+            [(_, "toggle"), rest @ ..] => (Action::Toggle, rest),
+            [(column, _), ..] => return error(*column, "Expected turn or toggle"),
+            [] => return error(0, "Empty instruction"),
+        };
+
+        let pos1 = position(rest.first(), line.len())?;
+        match rest.get(1) {
+            Some((_, "through")) => {},
+            Some((column, _)) => return error(*column, "Expected through"),
+            None => return error(line.len(), "Expected through"),
+        }
+        let pos2 = position(rest.get(2), line.len())?;
+        if let Some((column, _)) = rest.get(3) {
+            return error(*column, "Unexpected trailing input");
+        }
+        if pos1.0 > pos2.0 || pos1.1 > pos2.1 {
+            return error(rest[0].0, "First corner has to be the top left corner");
+        }
+
+        Ok(Instruction { pos1, pos2, action })
+    }
+}
+
 impl Instruction {
     pub fn new(xmin: usize, ymin: usize, xmax: usize, ymax: usize, keyword: &str) -> Self {
         Self {
@@ -49,64 +134,115 @@ impl Instruction {
 }
 
 #[aoc_generator(day6)]
-pub fn input_generator(input: &str) -> Vec<Instruction> {
-    let mut v: Vec<Instruction> = Vec::new();
-    for line in input.lines() {
-        let mut tokens = line.trim().split(|c| c == ' ' || c == ',');
-        let mut keyword = tokens.next().unwrap();
-        if keyword == "turn" {
-            keyword = tokens.next().unwrap();
-        };
-        let xmin: usize = tokens.next().unwrap().parse().unwrap();
-        let ymin: usize = tokens.next().unwrap().parse().unwrap();
-        tokens.next();
-        let xmax: usize = tokens.next().unwrap().parse().unwrap();
-        let ymax: usize = tokens.next().unwrap().parse().unwrap();
+pub fn input_generator(input: &str) -> Result<Vec<Instruction>, ParseError> {
+    input.lines()
+        .enumerate()
+        .map(|(idx, line)| line.parse().map_err(|error: ParseError| ParseError { line: idx + 1, ..error }))
+        .collect()
+}
 
-        v.push(Instruction::new(xmin , ymin , xmax, ymax, keyword))
+pub fn switch(action: Action, light: u32) -> u32 {
+    match action {
+        Action::Off => 0,
+        Action::On => 1,
+        Action::Toggle => 1 - light
     }
-    v
 }
 
-#[aoc(day6, part1)]
-pub fn solve_part1(input: &[Instruction]) -> u64 {
-    let mut lights = [[false; 1000]; 1000];
-
-    for instruction in input.iter() {
-        for row in &mut lights[instruction.get_xrange()] {
-            for light in &mut row[instruction.get_yrange()] {
-                *light = match instruction.action {
-                    Action::Off => false,
-                    Action::On => true,
-                    Action::Toggle => !*light
+pub fn brightness(action: Action, light: u32) -> u32 {
+    match action {
+        Action::Off => light.saturating_sub(1),
+        Action::On => light + 1,
+        Action::Toggle => light + 2
+    }
+}
+
+// Coordinate compressed grid: every instruction edge splits the plane, so each cell of the
+// compressed grid is a rectangle of lights that always went through the same instructions
+pub struct LightGrid {
+    xs: Vec<usize>,
+    ys: Vec<usize>,
+    cells: Vec<u32>,
+}
+
+impl LightGrid {
+    pub fn run(instructions: &[Instruction], operator: impl Fn(Action, u32) -> u32) -> Self {
+        let mut xs: Vec<usize> = instructions.iter().flat_map(|i| [i.pos1.0, i.pos2.0 + 1]).collect();
+        let mut ys: Vec<usize> = instructions.iter().flat_map(|i| [i.pos1.1, i.pos2.1 + 1]).collect();
+        xs.sort_unstable();
+        xs.dedup();
+        ys.sort_unstable();
+        ys.dedup();
+
+        let width = xs.len().saturating_sub(1);
+        let mut cells = vec![0; width * ys.len().saturating_sub(1)];
+        for instruction in instructions {
+            let index = |values: &[usize], value: usize| values.binary_search(&value).unwrap();
+            let columns = index(&xs, instruction.pos1.0)..index(&xs, instruction.pos2.0 + 1);
+            for row in index(&ys, instruction.pos1.1)..index(&ys, instruction.pos2.1 + 1) {
+                for light in &mut cells[row * width + columns.start..row * width + columns.end] {
+                    *light = operator(instruction.action, *light);
                 }
             }
         }
+        LightGrid { xs, ys, cells }
     }
-    let mut sum: u64 = 0;
-    for row in lights.iter() {
-        sum += row.iter().fold(0, |acc, &x| if x {acc + 1} else {acc})
+
+    fn rectangles(&self) -> impl Iterator<Item = (RangeInclusive<usize>, RangeInclusive<usize>, u32)> + '_ {
+        let width = self.xs.len().saturating_sub(1);
+        self.cells.iter().enumerate().map(move |(idx, &light)| {
+            let (row, column) = (idx / width, idx % width);
+            (self.xs[column]..=self.xs[column + 1] - 1, self.ys[row]..=self.ys[row + 1] - 1, light)
+        })
     }
-    sum
-}
 
-#[aoc(day6, part2)]
-pub fn solve_part2(input: &[Instruction]) -> u32 {
-    let mut lights = [[0u32; 1000]; 1000];
-
-    for instruction in input.iter() {
-        for row in &mut lights[instruction.get_xrange()] {
-            for light in &mut row[instruction.get_yrange()] {
-                *light = match instruction.action {
-                    Action::Off => light.saturating_sub(1),
-                    Action::On => *light + 1,
-                    Action::Toggle => *light + 2
-                }
+    pub fn total(&self) -> u64 {
+        self.rectangles()
+            .map(|(xs, ys, light)| light as u64 * xs.count() as u64 * ys.count() as u64)
+            .sum()
+    }
+
+    pub fn get(&self, (x, y): Position) -> u32 {
+        let column = self.xs.partition_point(|&start| start <= x);
+        let row = self.ys.partition_point(|&start| start <= y);
+        if column == 0 || column == self.xs.len() || row == 0 || row == self.ys.len() {
+            return 0;
+        }
+        self.cells[(row - 1) * (self.xs.len() - 1) + column - 1]
+    }
+
+    // Binary greyscale PGM of the lights from (0, 0) to the bottom right corner of the instructions,
+    // the brightest light is white. Every light is a pixel, so images above `max_pixels` are refused.
+    pub fn to_pgm(&self, max_pixels: usize) -> Result<Vec<u8>, String> {
+        let width = self.xs.last().copied().unwrap_or(0);
+        let height = self.ys.last().copied().unwrap_or(0);
+        match width.checked_mul(height) {
+            Some(pixels) if pixels <= max_pixels => {}
+            _ => return Err(format!("Image of {width}x{height} is larger than {max_pixels} pixels")),
+        }
+        let max = self.cells.iter().copied().max().unwrap_or(0).max(1) as u64;
+
+        let mut image = format!("P5\n{width} {height}\n255\n").into_bytes();
+        let header = image.len();
+        image.resize(header + width * height, 0);
+        for (xs, ys, light) in self.rectangles() {
+            let shade = (light as u64 * 255 / max) as u8;
+            for y in ys {
+                image[header + y * width + xs.start()..=header + y * width + xs.end()].fill(shade);
             }
         }
+        Ok(image)
     }
-    let count = lights.iter().fold(0, |acc, row| acc + row.iter().sum::<u32>());
-    count
+}
+
+#[aoc(day6, part1)]
+pub fn solve_part1(input: &[Instruction]) -> u64 {
+    LightGrid::run(input, switch).total()
+}
+
+#[aoc(day6, part2)]
+pub fn solve_part2(input: &[Instruction]) -> u64 {
+    LightGrid::run(input, brightness).total()
 }
 
 #[cfg(test)]
@@ -118,19 +254,54 @@ mod tests {
         let string = "turn on 0,0 through 999,999
         toggle 0,0 through 999,0
         turn off 499,499 through 500,500";
-        assert_eq!(solve_part1(&input_generator(string)), 998996);
+        assert_eq!(solve_part1(&input_generator(string).unwrap()), 998996);
     }
 
     #[test]
     fn example2() {
         let string = "turn on 0,0 through 0,0";
-        assert_eq!(solve_part2(&input_generator(string)), 1);
+        assert_eq!(solve_part2(&input_generator(string).unwrap()), 1);
     }
 
     #[test]
     fn example3() {
-        let string = "turn on 0,0 through 0,0\
+        let string = "turn on 0,0 through 0,0
         toggle 0,0 through 999,999";
-        assert_eq!(solve_part2(&input_generator(string)), 2000001);
+        assert_eq!(solve_part2(&input_generator(string).unwrap()), 2000001);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn large_coordinates() {
+        let string = "turn on 0,0 through 999999999,999999999
+        toggle 1000,1000 through 1999,1999";
+        let grid = LightGrid::run(&input_generator(string).unwrap(), switch);
+        assert_eq!(grid.total(), 1_000_000_000 * 1_000_000_000 - 1_000_000);
+        assert_eq!(grid.get((1500, 1999)), 0);
+        assert_eq!(grid.get((1500, 2000)), 1);
+    }
+
+    #[test]
+    fn malformed() {
+        let error = input_generator("turn on 0,0 through 0,0toggle 0,0 through 999,999").unwrap_err();
+        assert_eq!((error.line, error.column), (1, 23));
+        let error = input_generator("toggle 0,0 through 9,9\nturn up 0,0 through 1,1").unwrap_err();
+        assert_eq!(error.to_string(), "line 2, column 6: Expected on or off");
+        let error = input_generator("toggle 0,x through 9,9").unwrap_err();
+        assert_eq!(error.column, 10);
+        assert!(input_generator("toggle 5,5 through 1,1").is_err());
+        let error = input_generator(&format!("toggle 0,0 through 1,{}", usize::MAX)).unwrap_err();
+        assert_eq!(error.column, 22);
+        assert!(input_generator(&format!("toggle 0,0 through {},1", usize::MAX - 1)).is_ok());
+    }
+
+    #[test]
+    fn image() {
+        let grid = LightGrid::run(&input_generator("turn on 1,0 through 2,1\ntoggle 2,1 through 2,1").unwrap(), brightness);
+        assert_eq!(grid.to_pgm(6), Ok(b"P5\n3 2\n255\n\0\x55\x55\0\x55\xff".to_vec()));
+        assert!(grid.to_pgm(5).is_err());
+
+        // 10^10 x 10^10 pixels don't even fit in a usize
+        let huge = LightGrid::run(&input_generator("turn on 0,0 through 9999999999,9999999999").unwrap(), switch);
+        assert!(huge.to_pgm(usize::MAX).is_err());
+    }
+}
@@ -1,70 +1,147 @@
-#[aoc(day8, part1)]
-pub fn solve_part1(input: &str) -> usize {
-    input.lines().map(line_length).map(|(m, c)| m - c).sum()
+use std::error::Error;
+use std::fmt;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EscapeError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for EscapeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
 }
 
-fn line_length(line: &str) -> (usize, usize) {
-    let code_length = line.as_bytes().len();
-    let mut string_length = 0;
+impl Error for EscapeError {}
+
+fn error<T>(offset: usize, message: &str) -> Result<T, EscapeError> {
+    Err(EscapeError { offset, message: message.to_string() })
+}
 
-    // Already move the outer quotes of the string since they don't count in string length
-    let mut line = &line.as_bytes()[1..code_length - 1];
-    while !line.is_empty() {
-        let mut update = false;
-        if line.len() >= 2 {
-            let char = &line[0..2];
-            match char {
-                b"\\\\" | b"\\\"" => {
-                    line = &line[2..];
-                    update = true;
+// Decodes a quoted string literal with \\, \" and \xHH escapes into its bytes
+pub fn decode(literal: &str) -> Result<Vec<u8>, EscapeError> {
+    let code = literal.as_bytes();
+    if code.first() != Some(&b'"') {
+        return error(0, "Missing opening quote");
+    }
+
+    let mut bytes = vec![];
+    let mut idx = 1;
+    loop {
+        match code.get(idx) {
+            None => return error(idx, "Missing closing quote"),
+            Some(b'"') => break,
+            Some(b'\\') => match code.get(idx + 1) {
+                Some(&escaped @ (b'\\' | b'"')) => {
+                    bytes.push(escaped);
+                    idx += 2;
                 },
-                b"\\x" => {
-                    line = &line[4..];
-                    update = true;
+                Some(b'x') => {
+                    // from_str_radix alone would also accept a sign
+                    let hex = code.get(idx + 2..idx + 4)
+                        .filter(|hex| hex.iter().all(u8::is_ascii_hexdigit))
+                        .and_then(|hex| std::str::from_utf8(hex).ok())
+                        .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+                    let Some(byte) = hex else {
+                        return error(idx, "Invalid \\x escape, expected two hex digits");
+                    };
+                    bytes.push(byte);
+                    idx += 4;
                 },
-                _ => {}
-            }
-        }
-        if !update {
-            line = &line[1..];
+                Some(_) => return error(idx, "Unknown escape"),
+                None => return error(idx, "Unterminated escape"),
+            },
+            Some(&byte) => {
+                bytes.push(byte);
+                idx += 1;
+            },
         }
-        string_length += 1;
     }
-    (code_length, string_length)
+    if idx + 1 != code.len() {
+        return error(idx + 1, "Trailing characters after closing quote");
+    }
+    Ok(bytes)
 }
 
-#[aoc(day8, part2)]
-pub fn solve_part2(input: &str) -> usize {
-    input.lines().map(encode_length).map(|(m, e)| e - m).sum()
+// Quoted literal for any bytes, only quotes, backslashes and unprintable bytes are escaped
+pub fn encode(bytes: &[u8]) -> String {
+    let mut literal = String::from("\"");
+    for &byte in bytes {
+        match byte {
+            b'"' | b'\\' => {
+                literal.push('\\');
+                literal.push(byte as char);
+            },
+            b' '..=b'~' => literal.push(byte as char),
+            _ => literal += &format!("\\x{byte:02x}"),
+        }
+    }
+    literal.push('"');
+    literal
 }
 
-fn encode_length(line: &str) -> (usize, usize) {
-    let code_length = line.as_bytes().len();
-
-    let encode_length = code_length
-        + 2
-        + line
-        .as_bytes()
-        .iter()
-        .filter(|&ch| ch == &b'"' || ch == &b'\\')
-        .count();
+#[aoc(day8, part1)]
+pub fn solve_part1(input: &str) -> Result<usize, EscapeError> {
+    input.lines()
+        .map(|line| decode(line.trim()).map(|bytes| line.trim().len() - bytes.len()))
+        .sum()
+}
 
-    (code_length, encode_length)
+#[aoc(day8, part2)]
+pub fn solve_part2(input: &str) -> usize {
+    input.lines()
+        .map(|line| encode(line.trim().as_bytes()).len() - line.trim().len())
+        .sum()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    const EXAMPLE: &str = r#"""
+"abc"
+"aaa\"aaa"
+"\x27""#;
+
     #[test]
     fn example1() {
-        let string = "include_str!(\"test.txt\")";
-        assert_eq!(solve_part1(string), 12);
+        assert_eq!(solve_part1(EXAMPLE), Ok(12));
     }
 
     #[test]
     fn example2() {
-        let string = "include_str!(\"test.txt\")";
-        assert_eq!(solve_part2(string), 19);
+        assert_eq!(solve_part2(EXAMPLE), 19);
+    }
+
+    #[test]
+    fn invalid_escapes() {
+        assert_eq!(decode(r#""ab\x2""#).unwrap_err().offset, 3);
+        assert_eq!(decode(r#""ab\x"#).unwrap_err().offset, 3);
+        assert_eq!(decode(r#""\q""#).unwrap_err().offset, 1);
+        assert_eq!(decode(r#""\x+f""#).unwrap_err().offset, 1);
+        assert_eq!(decode(r#""a\x-1""#).unwrap_err().offset, 2);
+        assert_eq!(decode(r#""abc"#).unwrap_err().offset, 4);
+        assert_eq!(decode(r#""a"b""#).unwrap_err().offset, 3);
+        assert_eq!(decode("abc").unwrap_err().offset, 0);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn round_trip() {
+        // Small xorshift generator so the property test doesn't need extra dependencies
+        let mut state: u64 = 0x2545F4914F6CDD1D;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+        for _ in 0..1000 {
+            let length = (next() % 32) as usize;
+            let bytes: Vec<u8> = (0..length).map(|_| next() as u8).collect();
+            let literal = encode(&bytes);
+            assert_eq!(decode(&literal), Ok(bytes));
+            assert_eq!(encode(&decode(&literal).unwrap()), literal);
+        }
+    }
+}
@@ -1,60 +1,161 @@
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Rule {
+    // A run of this many increasing letters, like abc
+    Straight(usize),
+    Forbidden(Vec<char>),
+    // At least this many non-overlapping pairs, like aa and bb
+    Pairs(usize),
+    Not(Box<Rule>),
+    Any(Vec<Rule>),
+}
+
+impl Rule {
+    pub fn check(&self, password: &[char]) -> bool {
+        match self {
+            Rule::Straight(length) => straight(password, *length),
+            Rule::Forbidden(letters) => !password.iter().any(|c| letters.contains(c)),
+            Rule::Pairs(count) => pairs(password) >= *count,
+            Rule::Not(rule) => !rule.check(password),
+            Rule::Any(rules) => rules.iter().any(|rule| rule.check(password)),
+        }
+    }
+}
+
+fn straight(password: &[char], length: usize) -> bool {
+    if length <= 1 {
+        return password.len() >= length;
+    }
+    password.windows(length)
+        .any(|window| window.windows(2).all(|w| w[0] as u8 + 1 == w[1] as u8))
+}
+
+fn pairs(password: &[char]) -> usize {
+    let mut iter = password.iter().peekable();
+
+    let mut pairs = 0;
+    while let Some(current) = iter.next() {
+        if let Some(next) = iter.peek() {
+            if *next == current {
+                pairs += 1;
+                iter.next();
+            }
+        }
+    }
+    pairs
+}
+
+// A password is valid when every rule holds
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Policy {
+    pub rules: Vec<Rule>,
+}
+
+impl Policy {
+    pub fn santa() -> Self {
+        Policy {
+            rules: vec![Rule::Straight(3), Rule::Forbidden(vec!['i', 'o', 'l']), Rule::Pairs(2)],
+        }
+    }
+
+    pub fn is_valid(&self, password: &[char]) -> bool {
+        self.rules.iter().all(|rule| rule.check(password))
+    }
+
+    fn forbidden(&self) -> Vec<char> {
+        self.rules.iter()
+            .filter_map(|rule| match rule {
+                Rule::Forbidden(letters) => Some(letters.clone()),
+                _ => None,
+            })
+            .flatten()
+            .collect()
+    }
+
+    // Next valid password of the same length, `None` once the passwords run out at zzz...
+    pub fn next_valid(&self, password: &str) -> Option<String> {
+        let mut password = password.chars().collect::<Vec<char>>();
+        let forbidden = self.forbidden();
+
+        if !increment(&mut password) {
+            return None;
+        }
+        loop {
+            // Every password that keeps a forbidden letter is invalid, so skip all of them
+            // by bumping the first forbidden letter and starting over from aaa... after it
+            if let Some(idx) = password.iter().position(|c| forbidden.contains(c)) {
+                password[idx + 1..].fill('a');
+                if !increment(&mut password[..=idx]) {
+                    return None;
+                }
+                continue;
+            }
+            if self.is_valid(&password) {
+                return Some(password.iter().collect());
+            }
+            if !increment(&mut password) {
+                return None;
+            }
+        }
+    }
+
+    pub fn passwords(&self, start: &str) -> Passwords<'_> {
+        Passwords { policy: self, current: Some(start.to_string()) }
+    }
+}
+
+// The valid passwords that follow a password, in order
+pub struct Passwords<'a> {
+    policy: &'a Policy,
+    current: Option<String>,
+}
+
+impl Iterator for Passwords<'_> {
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+        self.current = self.policy.next_valid(self.current.as_ref()?);
+        self.current.clone()
+    }
+}
+
 #[aoc(day11, part1)]
 pub fn solve_part1(input: &str) -> String {
-    let mut password = input.chars().collect::<Vec<char>>();
-    let mut password = increment(&mut password);
-    while !is_valid(password) {
-        password = increment(password);
-    }
-    password.iter().collect()
+    Policy::santa().next_valid(input.trim()).unwrap()
 }
 
-pub fn increment(password: &mut Vec<char>) -> &mut Vec<char> {
+// Returns false when the password wrapped around from zzz... to aaa...
+pub fn increment(password: &mut [char]) -> bool {
     for current in password.iter_mut().rev() {
         if *current == 'z' {
             *current = 'a';
         } else {
             *current = (*current as u8 + 1) as char;
-            break;
+            return true;
         }
     }
-    password
+    false
 }
 
 pub fn increasing_letters(password: &[char]) -> bool {
-    password.iter()
-        .zip(password.iter().skip(1))
-        .zip(password.iter().skip(2))
-        .any(|((&a, &b), &c)| a as u8 == b as u8 - 1 && b as u8 == c as u8 - 1)
+    Rule::Straight(3).check(password)
 }
 
 pub fn confusing_letters(password: &[char]) -> bool {
-    password.iter()
-        .any(|c| matches!(c, 'i' | 'o' | 'l'))
+    !Rule::Forbidden(vec!['i', 'o', 'l']).check(password)
 }
 
 pub fn two_pairs(password: &[char]) -> bool {
-    let mut iter = password.iter().peekable();
-
-    let mut pairs = 0;
-    while let Some(current) = iter.next() {
-        if let Some(next) = iter.peek() {
-            if *next == current {
-                pairs += 1;
-                iter.next();
-            }
-        }
-    }
-    pairs >= 2
+    Rule::Pairs(2).check(password)
 }
 
 #[inline]
 pub fn is_valid(password: &[char]) -> bool {
-    increasing_letters(password) && !confusing_letters(password) && two_pairs(password)
+    Policy::santa().is_valid(password)
 }
 
 #[aoc(day11, part2)]
 pub fn solve_part2(input: &str) -> String {
-    solve_part1(&solve_part1(input))
+    Policy::santa().passwords(input.trim()).nth(1).unwrap()
 }
 
 #[cfg(test)]
@@ -86,4 +187,31 @@ mod tests {
     fn example5() {
         assert_eq!(solve_part1("ghijklmn"), "ghjaabcc")
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn skips_forbidden_letters() {
+        assert_eq!(solve_part2("abcdefgh"), "abcdffbb");
+        assert!(is_valid(&"abcdffaa".chars().collect::<Vec<char>>()));
+
+        // Every letter except 'z' is forbidden, the skipping jumps straight to the only valid password
+        let only_z = Policy { rules: vec![Rule::Forbidden(('a'..='y').collect())] };
+        assert_eq!(only_z.next_valid("abcdefgh"), Some(String::from("zzzzzzzz")));
+        assert_eq!(only_z.next_valid("zzzzzzzz"), None);
+
+        // Every letter is forbidden, so there is no valid password at all
+        let nothing = Policy { rules: vec![Rule::Forbidden(('a'..='z').collect())] };
+        assert_eq!(nothing.next_valid("abcdefgh"), None);
+        assert_eq!(nothing.passwords("abc").next(), None);
+    }
+
+    #[test]
+    fn custom_policy() {
+        let policy = Policy {
+            rules: vec![Rule::Straight(4), Rule::Not(Box::new(Rule::Any(vec![Rule::Pairs(1), Rule::Forbidden(vec!['a'])])))],
+        };
+        // Needs 4 increasing letters, no pairs and at least one 'a'
+        let passwords: Vec<String> = policy.passwords("zzzzz").take(1).collect();
+        assert!(passwords.is_empty());
+        assert_eq!(policy.passwords("aabcc").take(3).collect::<Vec<_>>(), vec!["abcda", "abcdb", "abcdc"]);
+    }
+}
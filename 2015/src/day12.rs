@@ -1,33 +1,269 @@
-use itertools::Itertools;
-use regex::Regex;
+use std::error::Error;
+use std::fmt;
 
-#[aoc(day12, part1)]
-pub fn solve_part1(input: &str) -> i64 {
-    let re = Regex::new(r"-?\d+").unwrap();
-    let matches: Vec<_> = re.find_iter(input).map(|m| m.as_str().parse().unwrap()).collect();
-    matches.iter().sum()
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Exclusion {
+    // Objects where one of the values is this string, like "red"
+    ObjectWithValue(String),
+    // Properties with this key, together with their value
+    Key(String),
+    // Containers nested deeper than this, the outermost container has depth 1
+    MaxDepth(usize),
 }
 
-#[aoc(day12, part2)]
-pub fn solve_part2(input: &str) -> i64 {
-    let json: serde_json::Value = serde_json::from_str(input).unwrap();
-    recursive_json(&json)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Excluded {
+    pub pointer: String,
+    pub exclusion: Exclusion,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Report {
+    pub sum: i128,
+    pub excluded: Vec<Excluded>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct JsonError {
+    pub offset: usize,
+    pub message: String,
+}
+
+impl fmt::Display for JsonError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} at offset {}", self.message, self.offset)
+    }
+}
+
+impl Error for JsonError {}
+
+// What a value adds to the sum of its parent, strings are kept for the exclusions
+enum Parsed {
+    Sum(i128),
+    String(String),
+}
+
+// Streaming pass over the document, every container only keeps its running sum
+struct Parser<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+    exclusions: &'a [Exclusion],
+    excluded: Vec<Excluded>,
+}
+
+// JSON pointer escaping (RFC 6901)
+fn pointer_segment(segment: &str) -> String {
+    format!("/{}", segment.replace('~', "~0").replace('/', "~1"))
 }
 
-fn recursive_json(json: &serde_json::Value) -> i64 {
-    if let Some(obj) = json.as_object() {
-        if obj.values().contains(&serde_json::Value::String(String::from("red"))) {
-            return 0
+impl Parser<'_> {
+    fn error<T>(&self, message: &str) -> Result<T, JsonError> {
+        Err(JsonError { offset: self.pos, message: message.to_string() })
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_whitespace() {
+            self.pos += 1;
         }
-        return obj.values().map(recursive_json).sum()
     }
-    if let Some(list) = json.as_array() {
-        return list.iter().map(recursive_json).sum()
+
+    fn peek(&mut self) -> Option<u8> {
+        self.skip_whitespace();
+        self.bytes.get(self.pos).copied()
     }
-    if let Some(value) = json.as_i64() {
-        return value;
+
+    fn expect(&mut self, byte: u8) -> Result<(), JsonError> {
+        if self.peek() != Some(byte) {
+            return self.error(&format!("Expected '{}'", byte as char));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn add(&self, sum: i128, value: i128) -> Result<i128, JsonError> {
+        sum.checked_add(value).map_or_else(|| self.error("Sum overflows i128"), Ok)
+    }
+
+    fn value(&mut self, pointer: &str, depth: usize) -> Result<Parsed, JsonError> {
+        match self.peek() {
+            Some(b'{') | Some(b'[') => self.container(pointer, depth + 1).map(Parsed::Sum),
+            Some(b'"') => self.string().map(Parsed::String),
+            Some(b'-' | b'0'..=b'9') => self.number().map(Parsed::Sum),
+            Some(b't') => self.literal("true").map(|_| Parsed::Sum(0)),
+            Some(b'f') => self.literal("false").map(|_| Parsed::Sum(0)),
+            Some(b'n') => self.literal("null").map(|_| Parsed::Sum(0)),
+            Some(_) => self.error("Unexpected character"),
+            None => self.error("Unexpected end of input"),
+        }
     }
-    0
+
+    fn container(&mut self, pointer: &str, depth: usize) -> Result<i128, JsonError> {
+        let object = self.peek() == Some(b'{');
+        let close = if object { b'}' } else { b']' };
+        self.pos += 1;
+        // Exclusions found inside an excluded container are covered by the container itself
+        let mark = self.excluded.len();
+        let mut exclusion = self.exclusions.iter()
+            .find(|exclusion| matches!(exclusion, Exclusion::MaxDepth(max) if depth > *max));
+        let mut sum = 0;
+
+        let mut index = 0;
+        while self.peek() != Some(close) {
+            if index > 0 {
+                self.expect(b',')?;
+            }
+            let (child, key) = if object {
+                let key = self.string()?;
+                self.expect(b':')?;
+                (format!("{pointer}{}", pointer_segment(&key)), Some(key))
+            } else {
+                (format!("{pointer}/{index}"), None)
+            };
+
+            let value_mark = self.excluded.len();
+            let value = self.value(&child, depth)?;
+            let skipped = key.and_then(|key| self.exclusions.iter()
+                .find(|exclusion| matches!(exclusion, Exclusion::Key(k) if *k == key)));
+            match (value, skipped) {
+                (_, Some(skipped)) => {
+                    // Same as for containers, the excluded value covers everything found inside it
+                    self.excluded.truncate(value_mark);
+                    self.excluded.push(Excluded { pointer: child, exclusion: skipped.clone() });
+                },
+                (Parsed::Sum(value), None) => sum = self.add(sum, value)?,
+                (Parsed::String(value), None) if object => {
+                    exclusion = exclusion.or(self.exclusions.iter()
+                        .find(|exclusion| matches!(exclusion, Exclusion::ObjectWithValue(v) if *v == value)));
+                },
+                _ => {}
+            }
+            index += 1;
+        }
+        self.pos += 1;
+
+        match exclusion {
+            Some(exclusion) => {
+                self.excluded.truncate(mark);
+                self.excluded.push(Excluded { pointer: pointer.to_string(), exclusion: exclusion.clone() });
+                Ok(0)
+            },
+            None => Ok(sum),
+        }
+    }
+
+    fn string(&mut self) -> Result<String, JsonError> {
+        self.expect(b'"')?;
+        let mut result = vec![];
+        loop {
+            match self.bytes.get(self.pos) {
+                None => return self.error("Unterminated string"),
+                Some(b'"') => break,
+                Some(b'\\') => {
+                    let unescaped = self.escape()?;
+                    result.extend_from_slice(unescaped.encode_utf8(&mut [0; 4]).as_bytes());
+                },
+                Some(&byte) => {
+                    result.push(byte);
+                    self.pos += 1;
+                },
+            }
+        }
+        self.pos += 1;
+        String::from_utf8(result).map_or_else(|_| self.error("Invalid UTF-8 in string"), Ok)
+    }
+
+    fn escape(&mut self) -> Result<char, JsonError> {
+        let unescaped = match self.bytes.get(self.pos + 1) {
+            Some(b'"') => '"',
+            Some(b'\\') => '\\',
+            Some(b'/') => '/',
+            Some(b'b') => '\u{8}',
+            Some(b'f') => '\u{c}',
+            Some(b'n') => '\n',
+            Some(b'r') => '\r',
+            Some(b't') => '\t',
+            Some(b'u') => return self.unicode_escape(),
+            Some(_) => return self.error("Invalid escape"),
+            None => return self.error("Unterminated escape"),
+        };
+        self.pos += 2;
+        Ok(unescaped)
+    }
+
+    // Characters outside the basic multilingual plane are escaped as a surrogate pair
+    fn unicode_escape(&mut self) -> Result<char, JsonError> {
+        let start = self.pos;
+        let code = match self.hex_escape()? {
+            high @ 0xD800..=0xDBFF => match self.hex_escape() {
+                Ok(low @ 0xDC00..=0xDFFF) => 0x10000 + ((high - 0xD800) << 10) + (low - 0xDC00),
+                _ => high,
+            },
+            code => code,
+        };
+        char::from_u32(code).map_or_else(|| {
+            self.pos = start;
+            self.error("Unpaired surrogate")
+        }, Ok)
+    }
+
+    fn hex_escape(&mut self) -> Result<u32, JsonError> {
+        match self.bytes.get(self.pos..self.pos + 6) {
+            Some([b'\\', b'u', digits @ ..]) if digits.iter().all(u8::is_ascii_hexdigit) => {
+                let code = u32::from_str_radix(std::str::from_utf8(digits).unwrap(), 16).unwrap();
+                self.pos += 6;
+                Ok(code)
+            },
+            _ => self.error("Invalid unicode escape"),
+        }
+    }
+
+    fn number(&mut self) -> Result<i128, JsonError> {
+        let start = self.pos;
+        if self.bytes[self.pos] == b'-' {
+            self.pos += 1;
+        }
+        while self.pos < self.bytes.len() && self.bytes[self.pos].is_ascii_digit() {
+            self.pos += 1;
+        }
+        if matches!(self.bytes.get(self.pos), Some(b'.' | b'e' | b'E')) {
+            return self.error("Only integers can be summed");
+        }
+        let number = std::str::from_utf8(&self.bytes[start..self.pos]).unwrap();
+        number.parse().map_or_else(|_| {
+            self.pos = start;
+            self.error("Invalid or too large number")
+        }, Ok)
+    }
+
+    fn literal(&mut self, literal: &str) -> Result<(), JsonError> {
+        if !self.bytes[self.pos..].starts_with(literal.as_bytes()) {
+            return self.error(&format!("Expected {literal}"));
+        }
+        self.pos += literal.len();
+        Ok(())
+    }
+}
+
+pub fn account(input: &str, exclusions: &[Exclusion]) -> Result<Report, JsonError> {
+    let mut parser = Parser { bytes: input.as_bytes(), pos: 0, exclusions, excluded: vec![] };
+    let sum = match parser.value("", 0)? {
+        Parsed::Sum(sum) => sum,
+        Parsed::String(_) => 0,
+    };
+    if parser.peek().is_some() {
+        return parser.error("Trailing characters");
+    }
+    Ok(Report { sum, excluded: parser.excluded })
+}
+
+#[aoc(day12, part1)]
+pub fn solve_part1(input: &str) -> Result<i128, JsonError> {
+    account(input, &[]).map(|report| report.sum)
+}
+
+#[aoc(day12, part2)]
+pub fn solve_part2(input: &str) -> Result<i128, JsonError> {
+    account(input, &[Exclusion::ObjectWithValue(String::from("red"))]).map(|report| report.sum)
 }
 
 #[cfg(test)]
@@ -36,7 +272,72 @@ mod tests {
 
     #[test]
     fn example1() {
-        assert_eq!(solve_part1("[1,2,3]"), 6);
-        assert_eq!(solve_part1("[[[3]]]"), 3);
+        assert_eq!(solve_part1("[1,2,3]"), Ok(6));
+        assert_eq!(solve_part1("[[[3]]]"), Ok(3));
+    }
+
+    #[test]
+    fn example2() {
+        assert_eq!(solve_part1(r#"{"a":{"b":4},"c":-1}"#), Ok(3));
+        assert_eq!(solve_part1(r#"{"a":[-1,1]}"#), Ok(0));
+        assert_eq!(solve_part1("[]"), Ok(0));
+        assert_eq!(solve_part2("[1,2,3]"), Ok(6));
+        assert_eq!(solve_part2(r#"[1,{"c":"red","b":2},3]"#), Ok(4));
+        assert_eq!(solve_part2(r#"{"d":"red","e":[1,2,3,4],"f":5}"#), Ok(0));
+        assert_eq!(solve_part2(r#"[1,"red",5]"#), Ok(6));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn exclusion_report() {
+        let input = r#"{"a":[1,{"c":"red","b":{"x":"red","y":2}}],"skip":7,"deep":[[[100]]],"a/b":5}"#;
+        let exclusions = [
+            Exclusion::ObjectWithValue(String::from("red")),
+            Exclusion::Key(String::from("skip")),
+            Exclusion::MaxDepth(3),
+        ];
+        let report = account(input, &exclusions).unwrap();
+        assert_eq!(report.sum, 6);
+        assert_eq!(report.excluded, vec![
+            Excluded { pointer: String::from("/a/1"), exclusion: exclusions[0].clone() },
+            Excluded { pointer: String::from("/skip"), exclusion: exclusions[1].clone() },
+            Excluded { pointer: String::from("/deep/0/0"), exclusion: exclusions[2].clone() },
+        ]);
+        assert_eq!(pointer_segment("a/b~c"), "/a~1b~0c");
+    }
+
+    #[test]
+    fn excluded_key_covers_its_value() {
+        let input = r#"{"skip":{"a":{"b":"red","c":1},"d":[{"e":"red"}],"f":2},"g":3}"#;
+        let exclusions = [
+            Exclusion::ObjectWithValue(String::from("red")),
+            Exclusion::Key(String::from("skip")),
+        ];
+        let report = account(input, &exclusions).unwrap();
+        assert_eq!(report.sum, 3);
+        assert_eq!(report.excluded, vec![
+            Excluded { pointer: String::from("/skip"), exclusion: exclusions[1].clone() },
+        ]);
+    }
+
+    #[test]
+    fn big_numbers() {
+        assert_eq!(solve_part1("[9223372036854775807,9223372036854775807]"), Ok(18446744073709551614));
+        assert!(solve_part1("[170141183460469231731687303715884105727,1]").is_err());
+        assert!(solve_part1("[1.5]").is_err());
+        assert_eq!(solve_part1("[1,").unwrap_err().offset, 3);
+    }
+
+    #[test]
+    fn escapes() {
+        assert_eq!(solve_part2(r#"{"a":"\red","b":5}"#), Ok(5));
+        assert_eq!(solve_part2(r#"{"a":"\u0072ed","b":5}"#), Ok(0));
+        assert_eq!(solve_part2(r#"{"a":"r\u0065d","b":5}"#), Ok(0));
+        let report = account(r#"{"\"\\\/\b\f\n\r\t\u00e9\ud83c\udf84":[1]}"#, &[Exclusion::Key(String::from("\"\\/\u{8}\u{c}\n\r\té🎄"))]);
+        assert_eq!(report.unwrap().sum, 0);
+
+        assert_eq!(solve_part1(r#"[1,"\q"]"#).unwrap_err().offset, 4);
+        assert_eq!(solve_part1(r#"[1,"\u00g0"]"#).unwrap_err().offset, 4);
+        assert_eq!(solve_part1(r#"[1,"\ud83c"]"#).unwrap_err().offset, 4);
+        assert_eq!(solve_part1(r#"[1,"\udf84"]"#).unwrap_err().offset, 4);
+    }
+}
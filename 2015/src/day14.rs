@@ -1,83 +1,190 @@
-use std::cmp;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::str::FromStr;
+
+const RACE: u32 = 2503;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reindeer {
+    name: String,
+    speed: u32,
+    fly: u32,
+    rest: u32,
+}
+
+impl Reindeer {
+    // Without any time in a fly/rest cycle the reindeer never gets anywhere in time
+    pub fn new(name: &str, speed: u32, fly: u32, rest: u32) -> Result<Self, String> {
+        match fly.checked_add(rest) {
+            Some(0) => return Err(format!("{name} neither flies nor rests")),
+            None => return Err(format!("{name} has a fly/rest cycle that is too long")),
+            Some(_) => {},
+        }
+        Ok(Reindeer { name: name.to_string(), speed, fly, rest })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    // Closed form, full fly/rest cycles and then whatever is left
+    pub fn distance(&self, time: u32) -> u32 {
+        let cycles = time / (self.fly + self.rest);
+        let left = time % (self.fly + self.rest);
+        cycles * self.speed * self.fly + self.speed * left.min(self.fly)
+    }
+}
+
+impl FromStr for Reindeer {
+    type Err = String;
+
+    // "Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds."
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let words: Vec<&str> = line.split_whitespace().collect();
+        let number = |index: usize| -> Result<u32, String> {
+            let word = words.get(index).ok_or_else(|| format!("Incomplete reindeer {line:?}"))?;
+            word.parse().map_err(|_| format!("Invalid number {word:?} in {line:?}"))
+        };
+        Reindeer::new(words.first().ok_or("Empty reindeer line")?, number(3)?, number(6)?, number(13)?)
+    }
+}
 
 #[aoc_generator(day14)]
-pub fn input_generator(input: &str) -> Vec<(u32, u32, u32)> {
-    let mut reindeer_stats: Vec<(u32, u32, u32)> = Vec::new();
-    for line in input.lines() {
-        let mut parts = line.trim().split(' ');
-        parts.next(); // name
-        parts.next(); // can
-        parts.next(); // fly
-        let speed = parts.next().unwrap().parse().unwrap();
-        parts.next(); // km/s
-        parts.next(); // for
-        let time = parts.next().unwrap().parse().unwrap();
-        parts.next(); // seconds,
-        parts.next(); // but
-        parts.next(); // then
-        parts.next(); // must
-        parts.next(); // rest
-        parts.next(); // for
-        let rest_time = parts.next().unwrap().parse().unwrap();
-        reindeer_stats.push((speed, time, rest_time));
-    }
-    reindeer_stats
+pub fn input_generator(input: &str) -> Result<Vec<Reindeer>, String> {
+    input.lines().map(|line| line.parse()).collect()
 }
 
-#[aoc(day14, part1)]
-pub fn solve_part1(reindeer_stats: &[(u32, u32, u32)]) -> u32 {
-    let mut max = u32::MIN;
-    let total_time = 2503;
-    for &(speed, time, rest_time) in reindeer_stats.iter() {
-        let mult = total_time / (time + rest_time);
-        let rest = total_time % (time + rest_time);
-        let result = mult * speed * time + speed * cmp::min(time, rest);
-        max = cmp::max(max, result);
-    }
-    max
+// Position of a reindeer at its last transition between flying and resting
+struct Runner {
+    position: u32,
+    since: u32,
+    flying: bool,
 }
 
-#[aoc(day14, part2)]
-pub fn solve_part2(reindeer_stats: &[(u32, u32, u32)]) -> u32 {
-    let mut race_stats: Vec<(u32, u32, u32)> = Vec::new();
-    let mut score: Vec<u32> = Vec::new();
-    for &(_speed, time, _rest_time) in reindeer_stats {
-        race_stats.push((0, time, 0));
-        score.push(0);
-    }
-    for _time in 0..2503 {
-        // Update the distance
-        let mut max = u32::MIN;
-        for (index, (speed, time, rest_time)) in reindeer_stats.iter().enumerate() {
-            let (dst, time_left, rest_time_left) = race_stats.get_mut(index).unwrap();
-            if *time_left > 0 {
-                *time_left -= 1;
-                if *time_left == 0 {
-                    *rest_time_left = *rest_time;
-                }
-                *dst += speed;
-            } else if *rest_time_left > 0 {
-                *rest_time_left -= 1;
-                if *rest_time_left == 0 {
-                    *time_left = *time;
-                }
-            } else {
-                unreachable!()
+// Event-driven race, only the moments a reindeer starts or stops flying are simulated
+pub struct Simulation<'a> {
+    reindeer: &'a [Reindeer],
+    time: u32,
+    runners: Vec<Runner>,
+    transitions: BinaryHeap<Reverse<(u32, usize)>>,
+}
+
+impl<'a> Simulation<'a> {
+    pub fn new(reindeer: &'a [Reindeer]) -> Self {
+        Simulation {
+            reindeer,
+            time: 0,
+            runners: reindeer.iter().map(|_| Runner { position: 0, since: 0, flying: true }).collect(),
+            transitions: reindeer.iter().enumerate().map(|(idx, r)| Reverse((r.fly, idx))).collect(),
+        }
+    }
+
+    // Jumps to `time`, which can't be earlier than the current time
+    pub fn advance(&mut self, time: u32) {
+        assert!(time >= self.time, "Simulation can't go back in time");
+        while let Some(&Reverse((at, idx))) = self.transitions.peek() {
+            if at > time {
+                break;
+            }
+            self.transitions.pop();
+            let (reindeer, runner) = (&self.reindeer[idx], &mut self.runners[idx]);
+            if runner.flying {
+                runner.position += reindeer.speed * (at - runner.since);
             }
-            if *dst > max {
-                max = *dst;
+            runner.since = at;
+            runner.flying = !runner.flying;
+            let duration = if runner.flying { reindeer.fly } else { reindeer.rest };
+            self.transitions.push(Reverse((at + duration, idx)));
+        }
+        self.time = time;
+    }
+
+    pub fn distances(&self) -> Vec<u32> {
+        self.reindeer.iter().zip(&self.runners)
+            .map(|(reindeer, runner)| match runner.flying {
+                true => runner.position + reindeer.speed * (self.time - runner.since),
+                false => runner.position,
+            })
+            .collect()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Second {
+    pub time: u32,
+    pub distances: Vec<u32>,
+    pub leaders: Vec<usize>,
+    pub points: Vec<u32>,
+}
+
+// Standings after every second of the race, both for the distance and for the points scoring
+pub struct Leaderboard {
+    pub names: Vec<String>,
+    pub timeline: Vec<Second>,
+}
+
+impl Leaderboard {
+    pub fn new(reindeer: &[Reindeer], duration: u32) -> Self {
+        let mut simulation = Simulation::new(reindeer);
+        let mut points = vec![0; reindeer.len()];
+        let mut timeline = Vec::with_capacity(duration as usize);
+
+        for time in 1..=duration {
+            simulation.advance(time);
+            let distances = simulation.distances();
+            let lead = distances.iter().copied().max().unwrap_or(0);
+            let leaders: Vec<usize> = (0..distances.len()).filter(|&idx| distances[idx] == lead).collect();
+            for &idx in &leaders {
+                points[idx] += 1;
             }
+            timeline.push(Second { time, distances, leaders, points: points.clone() });
         }
-        let indices = race_stats
-            .iter()
-            .enumerate()
-            .filter_map(|(index, &r)| (r.0 == max).then_some(index))
-            .collect::<Vec<_>>();
-        for index in indices {
-            *score.get_mut(index).unwrap() += 1;
+
+        Leaderboard {
+            names: reindeer.iter().map(|r| r.name.clone()).collect(),
+            timeline,
         }
     }
-    *score.iter().max().unwrap()
+
+    fn winner(&self, score: impl Fn(&Second) -> &[u32]) -> Option<(&str, u32)> {
+        let scores = score(self.timeline.last()?);
+        let (idx, &best) = scores.iter().enumerate().max_by_key(|(_, &score)| score)?;
+        Some((&self.names[idx], best))
+    }
+
+    pub fn winner_by_distance(&self) -> Option<(&str, u32)> {
+        self.winner(|second| &second.distances)
+    }
+
+    pub fn winner_by_points(&self) -> Option<(&str, u32)> {
+        self.winner(|second| &second.points)
+    }
+
+    pub fn to_csv(&self) -> String {
+        let mut csv = String::from("time");
+        for name in &self.names {
+            csv += &format!(",{name} distance,{name} points");
+        }
+        for second in &self.timeline {
+            csv += &format!("\n{}", second.time);
+            for (distance, points) in second.distances.iter().zip(&second.points) {
+                csv += &format!(",{distance},{points}");
+            }
+        }
+        csv
+    }
+}
+
+#[aoc(day14, part1)]
+pub fn solve_part1(reindeer: &[Reindeer]) -> u32 {
+    let mut simulation = Simulation::new(reindeer);
+    simulation.advance(RACE);
+    simulation.distances().into_iter().max().unwrap()
+}
+
+#[aoc(day14, part2)]
+pub fn solve_part2(reindeer: &[Reindeer]) -> u32 {
+    Leaderboard::new(reindeer, RACE).winner_by_points().unwrap().1
 }
 
 #[cfg(test)]
@@ -88,13 +195,56 @@ mod tests {
     fn example1() {
         let string = "Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds.
         Dancer can fly 16 km/s for 11 seconds, but then must rest for 162 seconds.";
-        assert_eq!(solve_part1(&input_generator(string)), 2660)
+        assert_eq!(solve_part1(&input_generator(string).unwrap()), 2660)
     }
 
     #[test]
     fn example2() {
         let string = "Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds.
         Dancer can fly 16 km/s for 11 seconds, but then must rest for 162 seconds.";
-        assert_eq!(solve_part2(&input_generator(string)), 1564)
+        assert_eq!(solve_part2(&input_generator(string).unwrap()), 1564)
+    }
+
+    const EXAMPLE: &str = "Comet can fly 14 km/s for 10 seconds, but then must rest for 127 seconds.
+Dancer can fly 16 km/s for 11 seconds, but then must rest for 162 seconds.";
+
+    #[test]
+    fn leaderboard_1000_seconds() {
+        let reindeer = input_generator(EXAMPLE).unwrap();
+        let leaderboard = Leaderboard::new(&reindeer, 1000);
+        assert_eq!(leaderboard.winner_by_distance(), Some(("Comet", 1120)));
+        assert_eq!(leaderboard.winner_by_points(), Some(("Dancer", 689)));
+
+        assert_eq!(leaderboard.timeline[0].leaders, vec![1]);
+        assert_eq!(leaderboard.timeline[139].points, vec![1, 139]);
+        assert!(leaderboard.to_csv().starts_with("time,Comet distance,Comet points,Dancer distance,Dancer points\n1,14,0,16,1\n2,28,0,32,2"));
+    }
+
+    #[test]
+    fn simulation_jumps() {
+        let reindeer = input_generator(EXAMPLE).unwrap();
+        let mut simulation = Simulation::new(&reindeer);
+        for time in [0, 1, 10, 11, 12, 137, 138, 1000, 2503, 100_000] {
+            simulation.advance(time);
+            let expected: Vec<u32> = reindeer.iter().map(|r| r.distance(time)).collect();
+            assert_eq!(simulation.distances(), expected);
+        }
+    }
+
+    #[test]
+    fn degenerate_cycles() {
+        assert!(input_generator("Idle can fly 10 km/s for 0 seconds, but then must rest for 0 seconds.").is_err());
+        assert!(input_generator("Comet can fly 14 km/s for ten seconds, but then must rest for 127 seconds.").is_err());
+        assert!(Reindeer::new("Idle", 10, 0, 0).is_err());
+        assert!(Reindeer::new("Endless", 10, u32::MAX, 1).is_err());
+
+        let reindeer = input_generator("Tireless can fly 3 km/s for 5 seconds, but then must rest for 0 seconds.
+Lazy can fly 9 km/s for 0 seconds, but then must rest for 4 seconds.").unwrap();
+        let mut simulation = Simulation::new(&reindeer);
+        simulation.advance(100);
+        assert_eq!(simulation.distances(), [300, 0]);
+        assert_eq!(reindeer[0].distance(100), 300);
+        assert_eq!(reindeer[1].distance(100), 0);
+        assert_eq!(reindeer[1].name(), "Lazy");
     }
-}
\ No newline at end of file
+}
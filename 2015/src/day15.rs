@@ -1,64 +1,196 @@
-use std::cmp;
-use std::iter::zip;
-use itertools::{any, iproduct};
+use std::str::FromStr;
 
 #[derive(Debug)]
 pub struct Ingredient {
-    capacity: i32,
-    durability: i32,
-    flavor: i32,
-    texture: i32,
-    calories: i32,
+    pub name: String,
+    pub properties: Vec<(String, i64)>,
 }
 
 impl From<&str> for Ingredient {
     fn from(value: &str) -> Self {
-        let value = value.replace(',', "");
-        let mut parts = value.split_whitespace().step_by(2).skip(1);
+        let (name, properties) = value.split_once(':').unwrap();
+        let properties = properties.split(',')
+            .map(|property| {
+                let (property, amount) = property.trim().split_once(' ').unwrap();
+                (property.to_string(), amount.trim().parse().unwrap())
+            })
+            .collect();
         Ingredient {
-            capacity: parts.next().unwrap().parse().unwrap(),
-            durability: parts.next().unwrap().parse().unwrap(),
-            flavor: parts.next().unwrap().parse().unwrap(),
-            texture: parts.next().unwrap().parse().unwrap(),
-            calories: parts.next().unwrap().parse().unwrap(),
+            name: name.trim().to_string(),
+            properties,
         }
     }
 }
 
-fn find_best(ingredients: &[Ingredient], num: i32, cal: bool) -> i32 {
-    let mut max_score = i32::MIN;
-    for (a, b, c) in iproduct!(0..num, 0..num, 0..num) {
-        let new_num = num - (a + b + c);
-        if new_num < 0 {
-            continue;
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparison {
+    Equal,
+    Less,
+    LessOrEqual,
+    Greater,
+    GreaterOrEqual,
+    NotEqual,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Constraint {
+    property: String,
+    comparison: Comparison,
+    value: i64,
+}
+
+impl FromStr for Constraint {
+    type Err = String;
+
+    // Constraints look like "calories == 500"
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut parts = input.split_whitespace();
+        let (Some(property), Some(comparison), Some(value), None) = (parts.next(), parts.next(), parts.next(), parts.next()) else {
+            return Err(format!("Invalid constraint {input:?}"));
+        };
+        let comparison = match comparison {
+            "==" => Comparison::Equal,
+            "<" => Comparison::Less,
+            "<=" => Comparison::LessOrEqual,
+            ">" => Comparison::Greater,
+            ">=" => Comparison::GreaterOrEqual,
+            "!=" => Comparison::NotEqual,
+            _ => return Err(format!("Invalid comparison {comparison:?}")),
+        };
+        let value = value.parse().map_err(|_| format!("Invalid value {value:?}"))?;
+        Ok(Constraint { property: property.to_string(), comparison, value })
+    }
+}
+
+impl Constraint {
+    // Whether some total in min..=max satisfies the constraint
+    fn possible(&self, min: i64, max: i64) -> bool {
+        match self.comparison {
+            Comparison::Equal => min <= self.value && self.value <= max,
+            Comparison::Less => min < self.value,
+            Comparison::LessOrEqual => min <= self.value,
+            Comparison::Greater => max > self.value,
+            Comparison::GreaterOrEqual => max >= self.value,
+            Comparison::NotEqual => min != self.value || max != self.value,
         }
-        let amounts = [a, b, c, new_num];
-        let score = calc_score(zip(ingredients, amounts), cal);
-        max_score = cmp::max(max_score, score);
     }
-    max_score
 }
 
-fn calc_score<'a>(amounts: impl Iterator<Item = (&'a Ingredient, i32)>, cal: bool) -> i32 {
-    let (mut total_cap, mut total_dur, mut total_flav, mut total_text, mut total_cal) = (0, 0, 0, 0, 0);
-    let max_calories = 500;
+// Calories don't count towards the score of a cookie
+const UNSCORED: &str = "calories";
+
+struct Kitchen<'a> {
+    values: Vec<Vec<i64>>,
+    scored: Vec<usize>,
+    constraints: Vec<(usize, &'a Constraint)>,
+    // Smallest and largest value of every property over the ingredients from an index onwards
+    suffix_min: Vec<Vec<i64>>,
+    suffix_max: Vec<Vec<i64>>,
+}
+
+impl<'a> Kitchen<'a> {
+    fn new(ingredients: &[Ingredient], constraints: &'a [Constraint]) -> Self {
+        let mut properties: Vec<&str> = vec![];
+        for ingredient in ingredients {
+            for (property, _) in &ingredient.properties {
+                if !properties.contains(&property.as_str()) {
+                    properties.push(property);
+                }
+            }
+        }
+        for constraint in constraints {
+            if !properties.contains(&constraint.property.as_str()) {
+                properties.push(&constraint.property);
+            }
+        }
 
-    for (ingredient, num) in amounts {
-        total_cap += ingredient.capacity * num;
-        total_dur += ingredient.durability * num;
-        total_flav += ingredient.flavor * num;
-        total_text += ingredient.texture * num;
-        total_cal += ingredient.calories * num;
+        let values: Vec<Vec<i64>> = ingredients.iter()
+            .map(|ingredient| properties.iter()
+                .map(|&property| ingredient.properties.iter()
+                    .find(|(name, _)| name == property)
+                    .map_or(0, |&(_, amount)| amount))
+                .collect())
+            .collect();
+
+        let mut suffix_min = vec![vec![0; properties.len()]; ingredients.len() + 1];
+        let mut suffix_max = vec![vec![0; properties.len()]; ingredients.len() + 1];
+        for i in (0..ingredients.len()).rev() {
+            for p in 0..properties.len() {
+                let last = i + 1 == ingredients.len();
+                suffix_min[i][p] = if last { values[i][p] } else { values[i][p].min(suffix_min[i + 1][p]) };
+                suffix_max[i][p] = if last { values[i][p] } else { values[i][p].max(suffix_max[i + 1][p]) };
+            }
+        }
+
+        Kitchen {
+            values,
+            scored: (0..properties.len()).filter(|&p| properties[p] != UNSCORED).collect(),
+            constraints: constraints.iter()
+                .map(|constraint| (properties.iter().position(|&p| p == constraint.property).unwrap(), constraint))
+                .collect(),
+            suffix_min,
+            suffix_max,
+        }
     }
-    let all = [total_cap, total_dur, total_flav, total_text];
-    if any(all, |x| x < 1) {
-        return 0;
+
+    fn score(&self, totals: &[i64]) -> i64 {
+        self.scored.iter().map(|&p| totals[p].max(0)).product()
     }
 
-    if cal && total_cal != max_calories {
-        return 0;
+    // Enumerates the compositions of the remaining teaspoons over the ingredients from `idx` onwards
+    fn search(&self, idx: usize, remaining: i64, totals: &mut [i64], amounts: &mut Vec<i64>, best: &mut Option<(Vec<i64>, i64)>) {
+        let last = idx + 1 == self.values.len();
+        let range = if last { remaining..=remaining } else { 0..=remaining };
+
+        for amount in range {
+            for (total, value) in totals.iter_mut().zip(&self.values[idx]) {
+                *total += amount * value;
+            }
+            amounts.push(amount);
+
+            if last {
+                let valid = self.constraints.iter().all(|(p, constraint)| constraint.possible(totals[*p], totals[*p]));
+                let score = self.score(totals);
+                if valid && best.as_ref().is_none_or(|(_, best)| score > *best) {
+                    *best = Some((amounts.clone(), score));
+                }
+            } else if self.promising(idx + 1, remaining - amount, totals, best) {
+                self.search(idx + 1, remaining - amount, totals, amounts, best);
+            }
+
+            amounts.pop();
+            for (total, value) in totals.iter_mut().zip(&self.values[idx]) {
+                *total -= amount * value;
+            }
+        }
     }
-    total_cap * total_dur * total_flav * total_text
+
+    // All remaining teaspoons have to be used, which bounds every total of the finished cookie
+    fn promising(&self, idx: usize, remaining: i64, totals: &[i64], best: &Option<(Vec<i64>, i64)>) -> bool {
+        let min = |p: usize| totals[p] + remaining * self.suffix_min[idx][p];
+        let max = |p: usize| totals[p] + remaining * self.suffix_max[idx][p];
+
+        if !self.constraints.iter().all(|&(p, constraint)| constraint.possible(min(p), max(p))) {
+            return false;
+        }
+        match best {
+            Some((_, best)) => self.scored.iter().map(|&p| max(p).max(0)).product::<i64>() > *best,
+            None => true,
+        }
+    }
+}
+
+// Best amount of teaspoons per ingredient together with the score of that cookie
+pub fn find_best(ingredients: &[Ingredient], teaspoons: i64, constraints: &[Constraint]) -> Option<(Vec<i64>, i64)> {
+    if ingredients.is_empty() {
+        return None;
+    }
+    let kitchen = Kitchen::new(ingredients, constraints);
+    let mut totals = vec![0; kitchen.suffix_min[0].len()];
+    let mut best = None;
+
+    kitchen.search(0, teaspoons, &mut totals, &mut vec![], &mut best);
+    best
 }
 
 
@@ -68,13 +200,14 @@ pub fn input_generator(input: &str) -> Vec<Ingredient> {
 }
 
 #[aoc(day15, part1)]
-pub fn solve_part1(ingredients: &[Ingredient]) -> i32 {
-    find_best(ingredients, 100, false)
+pub fn solve_part1(ingredients: &[Ingredient]) -> i64 {
+    find_best(ingredients, 100, &[]).unwrap().1
 }
 
 #[aoc(day15, part2)]
-pub fn solve_part2(ingredients: &[Ingredient]) -> i32 {
-    find_best(ingredients, 100, true)
+pub fn solve_part2(ingredients: &[Ingredient]) -> i64 {
+    let constraints = ["calories == 500".parse().unwrap()];
+    find_best(ingredients, 100, &constraints).unwrap().1
 }
 
 #[cfg(test)]
@@ -94,4 +227,57 @@ Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3";
 Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3";
         assert_eq!(solve_part2(&input_generator(input)), 57600000)
     }
-}
\ No newline at end of file
+
+    const THREE: &str = "Butterscotch: capacity -1, durability -2, flavor 6, texture 3, calories 8
+Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3
+Sugar: capacity 1, durability 0, flavor -1, texture 2, calories 2";
+
+    #[test]
+    fn best_amounts() {
+        let input = "Butterscotch: capacity -1, durability -2, flavor 6, texture 3, calories 8
+Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3";
+        let ingredients = input_generator(input);
+        assert_eq!(ingredients[1].name, "Cinnamon");
+        assert_eq!(find_best(&ingredients, 100, &[]), Some((vec![44, 56], 62842880)));
+        let constraints = ["calories == 500".parse().unwrap()];
+        assert_eq!(find_best(&ingredients, 100, &constraints), Some((vec![40, 60], 57600000)));
+    }
+
+    #[test]
+    fn three_ingredients() {
+        let ingredients = input_generator(THREE);
+        assert_eq!(find_best(&ingredients, 100, &[]), Some((vec![44, 56, 0], 62842880)));
+        let constraints = ["calories <= 400".parse().unwrap()];
+        assert_eq!(find_best(&ingredients, 100, &constraints), Some((vec![27, 38, 35], 29045520)));
+        let constraints = ["calories == 500".parse().unwrap(), "texture > 100".parse().unwrap()];
+        assert_eq!(find_best(&ingredients, 100, &constraints), Some((vec![43, 42, 15], 41670720)));
+    }
+
+    #[test]
+    fn impossible_constraint() {
+        let ingredients = input_generator(THREE);
+        let constraints = ["calories > 800".parse().unwrap()];
+        assert_eq!(find_best(&ingredients, 100, &constraints), None);
+        assert!("calories = 500".parse::<Constraint>().is_err());
+    }
+
+    #[test]
+    fn not_equal() {
+        let input = "Butterscotch: capacity -1, durability -2, flavor 6, texture 3, calories 8
+Cinnamon: capacity 2, durability 3, flavor -2, texture -1, calories 3";
+        let ingredients = input_generator(input);
+        // The best cookie without constraints has 44 * 8 + 56 * 3 = 520 calories
+        let constraints = ["calories != 520".parse().unwrap()];
+        let expected = (0..=100)
+            .filter(|butterscotch| butterscotch * 8 + (100 - butterscotch) * 3 != 520)
+            .map(|butterscotch| {
+                let cinnamon = 100 - butterscotch;
+                [(-1, 2), (-2, 3), (6, -2), (3, -1)].iter()
+                    .map(|(b, c)| (b * butterscotch + c * cinnamon).max(0))
+                    .product::<i64>()
+            })
+            .max();
+        assert_eq!(find_best(&ingredients, 100, &constraints).map(|(_, score)| score), expected);
+        assert_ne!(expected, Some(62842880));
+    }
+}
@@ -1,123 +1,166 @@
-use crate::day16::Match::{Equal, Greater, Less};
-
-#[derive(Default)]
-pub struct Info {
-    children: Option<u32>,
-    cats: Option<u32>,
-    samoyeds: Option<u32>,
-    pomeranians: Option<u32>,
-    akitas: Option<u32>,
-    vizslas: Option<u32>,
-    goldfish: Option<u32>,
-    trees: Option<u32>,
-    cars: Option<u32>,
-    perfumes: Option<u32>,
-    part_2: bool
+use std::collections::HashMap;
+
+const MFCSAM: &str = "children: 3, cats: 7, samoyeds: 2, pomeranians: 3, akitas: 0, vizslas: 0, goldfish: 5, trees: 3, cars: 2, perfumes: 1";
+
+// The real aunt has more cats and trees and fewer pomeranians and goldfish than the reading
+const RANGES: &str = "cats: >, trees: >, pomeranians: <, goldfish: <";
+
+pub struct Aunt {
+    number: usize,
+    attributes: HashMap<String, u32>,
 }
 
-impl Info {
-    pub fn new(children: u32, cats: u32, samoyeds: u32, pomeranians: u32, akitas: u32, vizslas: u32,
-               goldfish: u32, trees: u32, cars: u32, perfumes: u32) -> Self {
-        Self {
-            children: Some(children),
-            cats: Some(cats),
-            samoyeds: Some(samoyeds),
-            pomeranians: Some(pomeranians),
-            akitas: Some(akitas),
-            vizslas: Some(vizslas),
-            goldfish: Some(goldfish),
-            trees: Some(trees),
-            cars: Some(cars),
-            perfumes: Some(perfumes),
-            part_2: false
-        }
-    }
+// Splits "name: value, name: value" into its pairs
+fn pairs(input: &str) -> Result<Vec<(&str, &str)>, String> {
+    input.split([',', '\n'])
+        .filter(|pair| !pair.trim().is_empty())
+        .map(|pair| pair.split_once(':')
+            .map(|(name, value)| (name.trim(), value.trim()))
+            .ok_or(format!("Missing ':' in {pair:?}")))
+        .collect()
 }
 
-impl From<&str> for Info {
-    fn from(value: &str) -> Self {
-        let value = value.replace(',', ":");
-        let mut parts = value.split(':').skip(1);
-        let mut info = Info::default();
-        while let Some(object) = parts.next() {
-            let amount = parts.next().unwrap().trim().parse().unwrap();
-            match object.trim() {
-                "children" => info.children = Some(amount),
-                "cats" => info.cats = Some(amount),
-                "samoyeds" => info.samoyeds = Some(amount),
-                "pomeranians" => info.pomeranians = Some(amount),
-                "akitas" => info.akitas = Some(amount),
-                "vizslas" => info.vizslas = Some(amount),
-                "goldfish" => info.goldfish = Some(amount),
-                "trees" => info.trees = Some(amount),
-                "cars" => info.cars = Some(amount),
-                "perfumes" => info.perfumes = Some(amount),
-                _ => unreachable!()
-            }
-        }
-        info
+fn attributes(input: &str) -> Result<HashMap<String, u32>, String> {
+    pairs(input)?.into_iter()
+        .map(|(name, value)| value.parse()
+            .map(|value| (name.to_string(), value))
+            .map_err(|_| format!("Invalid amount {value:?} for {name}")))
+        .collect()
+}
+
+impl TryFrom<&str> for Aunt {
+    type Error = String;
+
+    fn try_from(value: &str) -> Result<Self, Self::Error> {
+        let (name, attributes_list) = value.split_once(':').ok_or(format!("Missing ':' in {value:?}"))?;
+        let number = name.trim().strip_prefix("Sue ")
+            .and_then(|number| number.parse().ok())
+            .ok_or(format!("Invalid aunt {name:?}"))?;
+        Ok(Aunt { number, attributes: attributes(attributes_list)? })
     }
 }
 
-enum Match {
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Comparator {
     Equal,
     Greater,
-    Less
+    Less,
 }
 
-impl PartialEq for Info {
-    fn eq(&self, other: &Info) -> bool {
-        let part_2 = self.part_2 || other.part_2;
-        compare(self.children, other.children, part_2, Equal)
-        && compare(self.cats, other.cats, part_2, Greater)
-        && compare(self.samoyeds, other.samoyeds, part_2, Equal)
-        && compare(self.pomeranians, other.pomeranians, part_2, Less)
-        && compare(self.akitas, other.akitas, part_2, Equal)
-        && compare(self.vizslas, other.vizslas, part_2, Equal)
-        && compare(self.goldfish, other.goldfish, part_2, Less)
-        && compare(self.trees, other.trees, part_2, Greater)
-        && compare(self.cars, other.cars, part_2, Equal)
-        && compare(self.perfumes, other.perfumes, part_2, Equal)
-    }
+pub struct Matcher {
+    reading: HashMap<String, u32>,
+    comparators: HashMap<String, Comparator>,
 }
 
-fn compare(this: Option<u32>, other: Option<u32>, part_2: bool, matched: Match) -> bool {
-    match (this, other) {
-        (Some(this), Some(other)) => {
-            if part_2 {
-                match matched {
-                    Equal => this == other,
-                    Greater => this > other,
-                    Less => this < other
+#[derive(Debug, PartialEq, Eq)]
+pub struct Candidate {
+    pub number: usize,
+    pub matched: usize,
+    pub mismatched: Vec<String>,
+}
+
+impl Matcher {
+    // Attributes without a comparator have to be equal to the reading
+    pub fn new(reading: &str, comparators: &str) -> Result<Self, String> {
+        let comparators = pairs(comparators)?.into_iter()
+            .map(|(name, comparator)| {
+                let comparator = match comparator {
+                    "==" => Comparator::Equal,
+                    ">" => Comparator::Greater,
+                    "<" => Comparator::Less,
+                    _ => return Err(format!("Invalid comparator {comparator:?} for {name}")),
+                };
+                Ok((name.to_string(), comparator))
+            })
+            .collect::<Result<_, String>>()?;
+        Ok(Matcher { reading: attributes(reading)?, comparators })
+    }
+
+    fn matches(&self, attribute: &str, amount: u32) -> Option<bool> {
+        let reading = *self.reading.get(attribute)?;
+        let matched = match self.comparators.get(attribute).unwrap_or(&Comparator::Equal) {
+            Comparator::Equal => amount == reading,
+            Comparator::Greater => amount > reading,
+            Comparator::Less => amount < reading,
+        };
+        Some(matched)
+    }
+
+    // All aunts, most likely first: fewest mismatched attributes, then most matched attributes.
+    // Attributes the MFCSAM didn't read count as neither.
+    pub fn candidates(&self, aunts: &[Aunt]) -> Vec<Candidate> {
+        let mut candidates: Vec<Candidate> = aunts.iter()
+            .map(|aunt| {
+                let mut matched = 0;
+                let mut mismatched = vec![];
+                for (attribute, &amount) in &aunt.attributes {
+                    match self.matches(attribute, amount) {
+                        Some(true) => matched += 1,
+                        Some(false) => mismatched.push(attribute.clone()),
+                        None => {}
+                    }
                 }
-            } else {
-                this == other
-            }
-        },
-        (Some(_this), None) => true,
-        (None, Some(_other)) => true,
-        (None, None) => true
+                mismatched.sort_unstable();
+                Candidate { number: aunt.number, matched, mismatched }
+            })
+            .collect();
+        candidates.sort_by_key(|candidate| (candidate.mismatched.len(), usize::MAX - candidate.matched, candidate.number));
+        candidates
+    }
+
+    // The aunt that matches every attribute, `None` when there is no such aunt or several of them
+    pub fn find(&self, aunts: &[Aunt]) -> Option<usize> {
+        let mut exact = self.candidates(aunts).into_iter()
+            .filter(|candidate| candidate.mismatched.is_empty());
+        match (exact.next(), exact.next()) {
+            (Some(aunt), None) => Some(aunt.number),
+            _ => None,
+        }
     }
 }
 
 #[aoc_generator(day16)]
-pub fn input_generator(input: &str) -> Vec<Info> {
-    input.lines().map(Info::from).collect()
+pub fn input_generator(input: &str) -> Vec<Aunt> {
+    input.lines().map(|line| Aunt::try_from(line).unwrap()).collect()
 }
 
 #[aoc(day16, part1)]
-pub fn solve_part1(aunts: &[Info]) -> usize {
-    let aunt_sue = Info::new(3, 7, 2, 3, 0, 0, 5, 3, 2, 1);
-    // Input is 1-indexed
-    aunts.iter()
-        .position(|info| info == &aunt_sue).unwrap() + 1
+pub fn solve_part1(aunts: &[Aunt]) -> Option<usize> {
+    Matcher::new(MFCSAM, "").unwrap().find(aunts)
 }
 
 #[aoc(day16, part2)]
-pub fn solve_part2(aunts: &[Info]) -> usize {
-    let mut aunt_sue = Info::new(3, 7, 2, 3, 0, 0, 5, 3, 2, 1);
-    aunt_sue.part_2 = true;
-    // Input is 1-indexed
-    aunts.iter()
-        .position(|info| info == &aunt_sue).unwrap() + 1
+pub fn solve_part2(aunts: &[Aunt]) -> Option<usize> {
+    Matcher::new(MFCSAM, RANGES).unwrap().find(aunts)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "Sue 1: goldfish: 6, trees: 9, akitas: 0
+Sue 2: cars: 2, perfumes: 1, trees: 3
+Sue 3: cats: 8, trees: 4, pomeranians: 2
+Sue 4: children: 3, cats: 7, tardigrades: 5";
+
+    #[test]
+    fn part1_example() {
+        assert_eq!(solve_part1(&input_generator(INPUT)), None);
+        assert_eq!(solve_part1(&input_generator(&INPUT[..INPUT.rfind('\n').unwrap()])), Some(2));
+    }
+
+    #[test]
+    fn part2_example() {
+        assert_eq!(solve_part2(&input_generator(INPUT)), Some(3));
+    }
+
+    #[test]
+    fn candidates() {
+        let matcher = Matcher::new(MFCSAM, RANGES).unwrap();
+        let candidates = matcher.candidates(&input_generator(INPUT));
+        assert_eq!(candidates[0], Candidate { number: 3, matched: 3, mismatched: vec![] });
+        assert_eq!(candidates[3], Candidate { number: 4, matched: 1, mismatched: vec![String::from("cats")] });
+        assert!(Aunt::try_from("Sue x: cats: 1").is_err());
+        assert!(Matcher::new(MFCSAM, "cats: >=").is_err());
+    }
 }
@@ -1,30 +1,71 @@
-use itertools::Itertools;
+#[aoc_generator(day17)]
+pub fn input_generator(input: &str) -> Vec<usize> {
+    input.lines().map(|line| line.trim().parse().unwrap()).collect()
+}
 
-#[aoc(day17, part1)]
-pub fn solve_part1(input: &str) -> usize {
-    let containers: Vec<usize> = input.lines().map(|line| line.parse().unwrap()).collect();
-
-    // Bruteforce ftw
-    let mut count = 0;
-    for i in 0..containers.len() {
-        count += containers.iter().combinations(i)
-            .filter(|comb| comb.iter().copied().sum::<usize>() == 150).count();
+// ways[k][liters] is the amount of ways to fill exactly `liters` with k containers.
+// Every container is its own item, so containers of the same size count as different ways.
+fn ways_by_count(containers: &[usize], target: usize) -> Vec<u64> {
+    let mut ways = vec![vec![0u64; target + 1]; containers.len() + 1];
+    ways[0][0] = 1;
+
+    for (used, &container) in containers.iter().enumerate() {
+        // Go backwards so every container is used at most once
+        for k in (0..=used).rev() {
+            for liters in (0..=target.saturating_sub(container)).rev() {
+                let count = ways[k][liters];
+                if count > 0 && liters + container <= target {
+                    ways[k + 1][liters + container] += count;
+                }
+            }
+        }
     }
-    count
+    ways.iter().map(|row| row[target]).collect()
+}
+
+// Amount of ways to store the eggnog and the amount of ways using the fewest containers
+pub fn solve(containers: &[usize], target: usize) -> (u64, u64) {
+    let ways = ways_by_count(containers, target);
+    let total = ways.iter().sum();
+    let fewest = ways.iter().copied().find(|&count| count > 0).unwrap_or(0);
+    (total, fewest)
+}
+
+#[aoc(day17, part1)]
+pub fn solve_part1(containers: &[usize]) -> u64 {
+    solve(containers, 150).0
 }
 
 #[aoc(day17, part2)]
-pub fn solve_part2(input: &str) -> usize {
-    let containers: Vec<usize> = input.lines().map(|line| line.parse().unwrap()).collect();
-
-    // Bruteforce ftw
-    let mut count = 0;
-    for i in 0..containers.len() {
-        count += containers.iter().combinations(i)
-            .filter(|comb| comb.iter().copied().sum::<usize>() == 150).count();
-        if count > 0 {
-            return count
-        }
+pub fn solve_part2(containers: &[usize]) -> u64 {
+    solve(containers, 150).1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const INPUT: &str = "20
+15
+10
+5
+5";
+
+    #[test]
+    fn example1() {
+        assert_eq!(solve(&input_generator(INPUT), 25).0, 4);
+    }
+
+    #[test]
+    fn example2() {
+        assert_eq!(solve(&input_generator(INPUT), 25).1, 3);
+    }
+
+    #[test]
+    fn all_containers() {
+        // Only using every container fills 55 liters, the old bruteforce never tried that
+        assert_eq!(solve(&input_generator(INPUT), 55), (1, 1));
+        assert_eq!(solve(&input_generator(INPUT), 56), (0, 0));
+        assert_eq!(ways_by_count(&input_generator(INPUT), 5), vec![0, 2, 0, 0, 0, 0]);
     }
-    unreachable!()
 }
@@ -47,6 +47,274 @@ fn part1(input: &str) -> usize {
     unreachable!()
 }
 
+// Molecules are sequences of elements: an uppercase letter followed by lowercase letters.
+// The start molecule `e` is a lowercase element on its own.
+fn tokenize(molecule: &str) -> Vec<&str> {
+    let mut tokens = vec![];
+    let mut start = 0;
+    for (idx, ch) in molecule.char_indices() {
+        if idx > start && ch.is_ascii_uppercase() {
+            tokens.push(&molecule[start..idx]);
+            start = idx;
+        }
+    }
+    if start < molecule.len() {
+        tokens.push(&molecule[start..]);
+    }
+    tokens
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Step {
+    pub from: String,
+    pub to: String,
+    pub molecule: String,
+}
+
+struct BinaryRule {
+    lhs: usize,
+    left: usize,
+    right: usize,
+    // 1 for the head of an original rule, 0 for the helper rules created while binarizing it
+    cost: u32,
+    rule: usize,
+}
+
+#[derive(Default, Clone)]
+struct Cell {
+    cost: Vec<u32>,
+    present: Vec<usize>,
+}
+
+// Derivation tree of an original replacement rule, leaves have no rule
+struct Node {
+    symbol: usize,
+    rule: Option<usize>,
+    children: Vec<Node>,
+}
+
+// The replacements form a context-free grammar where every element is a nonterminal that also derives itself.
+// Rules are binarized so a weighted CYK chart can find the minimal amount of replacements for every substring.
+pub struct Grammar {
+    symbols: Vec<String>,
+    index: HashMap<String, usize>,
+    elements: usize,
+    rules: Vec<(usize, Vec<usize>)>,
+    unit: Vec<(usize, usize, usize)>,
+    binary: Vec<BinaryRule>,
+    binary_by_left: Vec<Vec<usize>>,
+}
+
+impl Grammar {
+    pub fn new(replacements: &HashMap<&str, Vec<&str>>) -> Self {
+        let mut grammar = Grammar {
+            symbols: vec![],
+            index: HashMap::new(),
+            elements: 0,
+            rules: vec![],
+            unit: vec![],
+            binary: vec![],
+            binary_by_left: vec![],
+        };
+
+        let mut keys: Vec<_> = replacements.keys().collect();
+        keys.sort_unstable();
+        for key in keys {
+            for value in &replacements[key] {
+                let lhs = grammar.symbol(key);
+                let rhs: Vec<usize> = tokenize(value).into_iter().map(|token| grammar.symbol(token)).collect();
+                assert!(!rhs.is_empty(), "Replacement {key} => {value} produces nothing");
+                grammar.rules.push((lhs, rhs));
+            }
+        }
+        // Helper symbols are only added after all elements so anything from `elements` onwards is a helper
+        grammar.elements = grammar.symbols.len();
+        for rule in 0..grammar.rules.len() {
+            let (lhs, rhs) = grammar.rules[rule].clone();
+            if rhs.len() == 1 {
+                grammar.unit.push((lhs, rhs[0], rule));
+                continue;
+            }
+            let mut lhs = lhs;
+            let mut cost = 1;
+            for (i, &left) in rhs[..rhs.len() - 2].iter().enumerate() {
+                let helper = grammar.symbol(&format!("{}#{}", rule, i));
+                grammar.binary.push(BinaryRule { lhs, left, right: helper, cost, rule });
+                lhs = helper;
+                cost = 0;
+            }
+            let (left, right) = (rhs[rhs.len() - 2], rhs[rhs.len() - 1]);
+            grammar.binary.push(BinaryRule { lhs, left, right, cost, rule });
+        }
+
+        grammar.binary_by_left = vec![vec![]; grammar.symbols.len()];
+        for (idx, rule) in grammar.binary.iter().enumerate() {
+            grammar.binary_by_left[rule.left].push(idx);
+        }
+        grammar
+    }
+
+    fn symbol(&mut self, name: &str) -> usize {
+        if let Some(&idx) = self.index.get(name) {
+            return idx;
+        }
+        self.symbols.push(name.to_string());
+        self.index.insert(name.to_string(), self.symbols.len() - 1);
+        self.symbols.len() - 1
+    }
+
+    fn chart(&self, tokens: &[&str]) -> Vec<Cell> {
+        let n = tokens.len();
+        let mut chart = vec![Cell::default(); (n + 1) * (n + 1)];
+
+        for len in 1..=n {
+            for i in 0..=n - len {
+                let j = i + len;
+                let mut cost = vec![u32::MAX; self.symbols.len()];
+
+                if len == 1 {
+                    if let Some(&symbol) = self.index.get(tokens[i]) {
+                        cost[symbol] = 0;
+                    }
+                }
+                for k in i + 1..j {
+                    let (left, right) = (&chart[i * (n + 1) + k], &chart[k * (n + 1) + j]);
+                    for &b in &left.present {
+                        for &rule in &self.binary_by_left[b] {
+                            let rule = &self.binary[rule];
+                            if right.cost[rule.right] == u32::MAX {
+                                continue;
+                            }
+                            let total = left.cost[b] + right.cost[rule.right] + rule.cost;
+                            if total < cost[rule.lhs] {
+                                cost[rule.lhs] = total;
+                            }
+                        }
+                    }
+                }
+                // Unit rules can form cycles, but every application costs a step so this settles
+                let mut changed = true;
+                while changed {
+                    changed = false;
+                    for &(lhs, rhs, _) in &self.unit {
+                        if cost[rhs] != u32::MAX && cost[rhs] + 1 < cost[lhs] {
+                            cost[lhs] = cost[rhs] + 1;
+                            changed = true;
+                        }
+                    }
+                }
+
+                let present = (0..cost.len()).filter(|&s| cost[s] != u32::MAX).collect();
+                chart[i * (n + 1) + j] = Cell { cost, present };
+            }
+        }
+        chart
+    }
+
+    pub fn min_steps(&self, start: &str, molecule: &str) -> Option<usize> {
+        let tokens = tokenize(molecule);
+        let start = *self.index.get(start)?;
+        let n = tokens.len();
+        if n == 0 {
+            return None;
+        }
+
+        let chart = self.chart(&tokens);
+        match chart[n].cost[start] {
+            u32::MAX => None,
+            steps => Some(steps as usize),
+        }
+    }
+
+    pub fn derivation(&self, start: &str, molecule: &str) -> Option<Vec<Step>> {
+        let tokens = tokenize(molecule);
+        let start = *self.index.get(start)?;
+        let n = tokens.len();
+        if n == 0 {
+            return None;
+        }
+
+        let chart = self.chart(&tokens);
+        let steps = chart[n].cost[start];
+        if steps == u32::MAX {
+            return None;
+        }
+        let tree = self.build(&chart, &tokens, 0, n, start, steps);
+
+        let mut form = vec![self.symbols[start].clone()];
+        let mut result = vec![];
+        self.replay(&tree, 0, &mut form, &mut result);
+        Some(result)
+    }
+
+    fn build(&self, chart: &[Cell], tokens: &[&str], i: usize, j: usize, symbol: usize, cost: u32) -> Node {
+        let n = tokens.len();
+        if j == i + 1 && cost == 0 && self.symbols[symbol] == tokens[i] {
+            return Node { symbol, rule: None, children: vec![] };
+        }
+        let cell = &chart[i * (n + 1) + j];
+        // Unit steps strictly decrease the cost so following them can't loop
+        for &(lhs, rhs, rule) in &self.unit {
+            if lhs == symbol && cell.cost[rhs] != u32::MAX && cell.cost[rhs] + 1 == cost {
+                let child = self.build(chart, tokens, i, j, rhs, cost - 1);
+                return Node { symbol, rule: Some(rule), children: vec![child] };
+            }
+        }
+        let (rule, children) = self.expand(chart, tokens, i, j, symbol, cost);
+        Node { symbol, rule: Some(rule), children }
+    }
+
+    // Finds the binary rule used for `symbol` over i..j and flattens helper symbols back into the original rule
+    fn expand(&self, chart: &[Cell], tokens: &[&str], i: usize, j: usize, symbol: usize, cost: u32) -> (usize, Vec<Node>) {
+        let n = tokens.len();
+        for k in i + 1..j {
+            let (left, right) = (&chart[i * (n + 1) + k], &chart[k * (n + 1) + j]);
+            for binary in self.binary.iter().filter(|rule| rule.lhs == symbol) {
+                let (l, r) = (left.cost[binary.left], right.cost[binary.right]);
+                if l == u32::MAX || r == u32::MAX || l + r + binary.cost != cost {
+                    continue;
+                }
+                let mut children = vec![self.build(chart, tokens, i, k, binary.left, l)];
+                if binary.right >= self.elements {
+                    children.extend(self.expand(chart, tokens, k, j, binary.right, r).1);
+                } else {
+                    children.push(self.build(chart, tokens, k, j, binary.right, r));
+                }
+                return (binary.rule, children);
+            }
+        }
+        unreachable!()
+    }
+
+    // Leftmost derivation: expand a node, then its children from left to right
+    fn replay(&self, node: &Node, pos: usize, form: &mut Vec<String>, steps: &mut Vec<Step>) -> usize {
+        let Some(rule) = node.rule else {
+            return pos + 1;
+        };
+        let children: Vec<String> = node.children.iter().map(|child| self.symbols[child.symbol].clone()).collect();
+        form.splice(pos..pos + 1, children.iter().cloned());
+        steps.push(Step {
+            from: self.symbols[self.rules[rule].0].clone(),
+            to: children.concat(),
+            molecule: form.concat(),
+        });
+
+        let mut pos = pos;
+        for child in &node.children {
+            pos = self.replay(child, pos, form, steps);
+        }
+        pos
+    }
+}
+
+#[aoc(day19, part2)]
+fn part2(input: &str) -> Option<usize> {
+    let (rules, molecule) = input.split_once("\n\n")?;
+    let grammar = Grammar::new(&parse(rules));
+
+    grammar.min_steps("e", molecule.trim())
+}
+
 #[aoc(day19, part2, backward)]
 fn part2_b(input: &str) -> usize {
     if let Some((rules, molecule)) = input.split_once("\n\n") {
@@ -137,4 +405,73 @@ O => HH
 HOHOHO";
         assert_eq!(part2_b(input), 6);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn part2_chart_example1() {
+        let input = "e => H
+e => O
+H => HO
+H => OH
+O => HH
+
+HOH";
+        assert_eq!(part2(input), Some(3));
+    }
+
+    #[test]
+    fn part2_chart_example2() {
+        let input = "e => H
+e => O
+H => HO
+H => OH
+O => HH
+
+HOHOHO";
+        assert_eq!(part2(input), Some(6));
+    }
+
+    #[test]
+    fn part2_unreachable() {
+        let input = "e => H
+H => HH
+
+HO";
+        assert_eq!(part2(input), None);
+    }
+
+    #[test]
+    fn tokenize_elements() {
+        assert_eq!(tokenize("CRnCaSiRnBSiRnFAr"), vec!["C", "Rn", "Ca", "Si", "Rn", "B", "Si", "Rn", "F", "Ar"]);
+        assert_eq!(tokenize("e"), vec!["e"]);
+    }
+
+    #[test]
+    fn derivation_replays() {
+        let rules = "e => H
+e => O
+H => HO
+H => OH
+O => HH
+H => CRnHAr";
+        let rules = parse(rules);
+        let grammar = Grammar::new(&rules);
+        let molecule = "CRnHOArO";
+
+        let steps = grammar.derivation("e", molecule).unwrap();
+        assert_eq!(Some(steps.len()), grammar.min_steps("e", molecule));
+
+        let mut current = String::from("e");
+        for step in &steps {
+            assert!(rules[step.from.as_str()].contains(&step.to.as_str()));
+            let replaced = current.match_indices(&step.from)
+                .any(|(pos, _)| {
+                    let mut next = current.clone();
+                    next.replace_range(pos..pos + step.from.len(), &step.to);
+                    next == step.molecule
+                });
+            assert!(replaced);
+            current = step.molecule.clone();
+        }
+        assert_eq!(current, molecule);
+    }
+}
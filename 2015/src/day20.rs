@@ -1,50 +1,133 @@
 use aoc_runner_derive::aoc;
 
-#[aoc(day20, part1)]
-fn part1(input: &str) -> usize {
-    let number: usize = input.parse().unwrap();
+// Every elf delivers `multiplier` times its number to each multiple of its number,
+// but stops after `limit` houses when there is a limit.
+pub struct Sieve {
+    pub multiplier: u64,
+    pub limit: Option<usize>,
+}
+
+impl Sieve {
+    // Presents for every house below `bound`, house 0 gets nothing
+    pub fn presents(&self, bound: usize) -> Vec<u64> {
+        match self.limit {
+            None => sigma(bound).into_iter().map(|sum| sum * self.multiplier).collect(),
+            Some(limit) => {
+                let mut presents = vec![0; bound];
+                for elf in 1..bound {
+                    for house in (elf..bound).step_by(elf).take(limit) {
+                        presents[house] += elf as u64 * self.multiplier;
+                    }
+                }
+                presents
+            }
+        }
+    }
 
-    let mut presents = vec![10; number / 10];
-    for elf in 2..number / 10 {
-        for house in (elf..number / 10).step_by(elf) {
-            presents[house] += elf * 10;
+    pub fn first_house(&self, target: u64) -> Option<usize> {
+        if self.multiplier == 0 {
+            return if target == 0 { Some(1) } else { None };
         }
-        if presents[elf] >= number {
-            return elf;
+        // Elf n always visits house n, so that house is guaranteed to get enough presents
+        let max_bound = target.div_ceil(self.multiplier).max(1) as usize + 1;
+        let mut bound = estimate(max_bound).min(max_bound);
+
+        loop {
+            let presents = self.presents(bound);
+            if let Some(house) = (1..bound).find(|&house| presents[house] >= target) {
+                return Some(house);
+            }
+            if bound == max_bound {
+                return None;
+            }
+            bound = (bound * 2).min(max_bound);
         }
     }
-    unreachable!()
-    // Takes too damm long
-    // for house in 1..number / 10 {
-    //     let mut presents = 0;
-    //     for elf in 1..=house {
-    //         if house % elf == 0 {
-    //             presents += 10 * elf;
-    //         }
-    //     }
-    //     if presents >= number {
-    //         return house;
-    //     }
-    // }
 }
 
-#[aoc(day20, part2)]
-fn part2(input: &str) -> usize {
-    let number: usize = input.parse().unwrap();
-
-    let mut presents = vec![10; number / 10];
-    for elf in 2..number / 10 {
-        let mut visited = 0;
-        for house in (elf..number / 10).step_by(elf) {
-            presents[house] += elf * 11;
-            visited += 1;
-            if visited == 50 {
+// The divisor sum of n grows like e^gamma * n * ln(ln(n)) at most (Robin's inequality),
+// so the first house that gets n times the multiplier can't be much smaller than this
+fn estimate(n: usize) -> usize {
+    if n < 16 {
+        return n;
+    }
+    let n = n as f64;
+    (n / (1.7811 * n.ln().ln())) as usize
+}
+
+// Sum of divisors of every number below `bound` with a linear sieve, using that sigma is multiplicative.
+// power[n] is the largest power of the smallest prime of n that divides n and power_sum[n] its divisor sum.
+pub fn sigma(bound: usize) -> Vec<u64> {
+    let mut sigma = vec![0u64; bound];
+    let mut power = vec![0u64; bound];
+    let mut power_sum = vec![0u64; bound];
+    let mut primes: Vec<usize> = vec![];
+    if bound > 1 {
+        sigma[1] = 1;
+    }
+
+    for i in 2..bound {
+        if sigma[i] == 0 {
+            primes.push(i);
+            sigma[i] = i as u64 + 1;
+            power[i] = i as u64;
+            power_sum[i] = i as u64 + 1;
+        }
+        for &p in &primes {
+            let n = i * p;
+            if n >= bound {
                 break;
             }
+            if i % p == 0 {
+                power[n] = power[i] * p as u64;
+                power_sum[n] = power_sum[i] + power[n];
+                sigma[n] = sigma[i] / power_sum[i] * power_sum[n];
+                break;
+            }
+            power[n] = p as u64;
+            power_sum[n] = p as u64 + 1;
+            sigma[n] = sigma[i] * (p as u64 + 1);
         }
-        if presents[elf] >= number {
-            return elf;
-        }
     }
-    unreachable!()
+    sigma
+}
+
+#[aoc(day20, part1)]
+fn part1(input: &str) -> usize {
+    let sieve = Sieve { multiplier: 10, limit: None };
+    sieve.first_house(input.trim().parse().unwrap()).unwrap()
+}
+
+#[aoc(day20, part2)]
+fn part2(input: &str) -> usize {
+    let sieve = Sieve { multiplier: 11, limit: Some(50) };
+    sieve.first_house(input.trim().parse().unwrap()).unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn part1_example() {
+        let sieve = Sieve { multiplier: 10, limit: None };
+        assert_eq!(sieve.presents(10), vec![0, 10, 30, 40, 70, 60, 120, 80, 150, 130]);
+        assert_eq!(part1("70"), 4);
+        assert_eq!(part1("130"), 8);
+        assert_eq!(part1("29000000"), 665280);
+    }
+
+    #[test]
+    fn part2_example() {
+        let sieve = Sieve { multiplier: 11, limit: Some(2) };
+        // Elf 1 only visits houses 1 and 2, elf 2 only visits houses 2 and 4
+        assert_eq!(sieve.presents(7), vec![0, 11, 33, 33, 66, 55, 99]);
+        assert_eq!(part2("29000000"), 705600);
+    }
+
+    #[test]
+    fn sigma_matches_sweep() {
+        let sweep = Sieve { multiplier: 1, limit: Some(usize::MAX) }.presents(2000);
+        assert_eq!(sigma(2000), sweep);
+    }
 }
@@ -1,239 +1,34 @@
-use std::cmp::Reverse;
-use std::collections::{BinaryHeap, HashSet};
-
 use aoc_runner_derive::{aoc, aoc_generator};
 
-#[derive(Ord, PartialOrd, Eq, PartialEq, Clone, Copy, Hash)]
-struct State {
-    boss_hp: i32,
-    boss_damage: i32,
-    player_hp: i32,
-    player_mana: i32,
-    shield_effect: u8,
-    poison_effect: u8,
-    recharge_effect: u8,
-}
-
-impl State {
-    fn new(boss_hp: i32, boss_damage: i32) -> Self {
-        State {
-            boss_hp,
-            boss_damage,
-            player_hp: 50,
-            player_mana: 500,
-            shield_effect: 0,
-            poison_effect: 0,
-            recharge_effect: 0,
-        }
-    }
-
-    fn apply_spell_effects(&mut self) {
-        if self.shield_effect > 0 {
-            self.shield_effect -= 1;
-        }
-        if self.poison_effect > 0 {
-            self.poison_effect -= 1;
-            self.boss_hp -= 3;
-        }
-        if self.recharge_effect > 0 {
-            self.recharge_effect -= 1;
-            self.player_mana += 101;
-        }
-    }
-
-    fn boss_attack(&mut self) {
-        let mut attack = self.boss_damage;
-        if self.shield_effect > 0 {
-            attack = (self.boss_damage - 7).max(1);
-        }
-
-        self.player_hp -= attack;
-    }
-
-    fn player_alive(&self) -> bool {
-        self.player_hp > 0
-    }
-
-    fn boss_dead(&self) -> bool {
-        self.boss_hp <= 0
-    }
-
-    fn magic_missile(&mut self) -> Option<(i32, bool)> {
-        if self.player_mana >= 53 {
-            self.boss_hp -= 4;
-            self.player_mana -= 53;
-
-            self.apply_spell_effects();
-            self.boss_attack();
-            return Some((53, self.boss_dead()));
-        }
-        None
-    }
-
-    fn drain(&mut self) -> Option<(i32, bool)> {
-        if self.player_mana >= 73 {
-            self.boss_hp -= 2;
-            self.player_hp += 2;
-            self.player_mana -= 73;
-
-            self.apply_spell_effects();
-            self.boss_attack();
-            return Some((73, self.boss_dead()));
-        }
-        None
-    }
-
-    fn shield(&mut self) -> Option<(i32, bool)> {
-        if self.player_mana >= 113 && self.shield_effect == 0 {
-            self.shield_effect = 6;
-            self.player_mana -= 113;
-
-            self.apply_spell_effects();
-            self.boss_attack();
-            return Some((113, self.boss_dead()));
-        }
-        None
-    }
-
-    fn poison(&mut self) -> Option<(i32, bool)> {
-        if self.player_mana >= 173 && self.poison_effect == 0 {
-            self.poison_effect = 6;
-            self.player_mana -= 173;
-
-            self.apply_spell_effects();
-            self.boss_attack();
-            return Some((173, self.boss_dead()));
-        }
-        None
-    }
-
-    fn recharge(&mut self) -> Option<(i32, bool)> {
-        if self.player_mana >= 229 && self.recharge_effect == 0 {
-            self.recharge_effect = 5;
-            self.player_mana -= 229;
-
-            self.apply_spell_effects();
-            self.boss_attack();
-            return Some((229, self.boss_dead()));
-        }
-        None
-    }
-}
+use crate::rpg::{Battle, parse_spells, DEFAULT_SPELLS, Stats};
 
+const PLAYER: Stats = Stats { hp: 50, damage: 0, armor: 0, mana: 500 };
 
 #[aoc_generator(day22)]
-fn parse(input: &str) -> [i32; 2] {
-    let mut boss_stats: [i32; 2] = [0; 2];
-
-    for (index, line) in input.lines().enumerate() {
-        if let Some((_stat, value)) = line.split_once(": ") {
-            boss_stats[index] = value.parse().unwrap();
-        } else {
-            unreachable!()
-        }
-    }
-
-    boss_stats
+fn parse(input: &str) -> Stats {
+    input.parse().unwrap()
 }
 
 #[aoc(day22, part1)]
-fn part1(input: &[i32; 2]) -> i32 {
-    let [boss_hp, boss_damage] = input;
-    let start = State::new(*boss_hp, *boss_damage);
+fn part1(boss: &Stats) -> i32 {
+    let battle = Battle::new(parse_spells(DEFAULT_SPELLS).unwrap(), PLAYER, *boss);
 
-    play(start, false)
+    battle.min_mana().unwrap()
 }
 
 #[aoc(day22, part2)]
-fn part2(input: &[i32; 2]) -> i32 {
-    let [boss_hp, boss_damage] = input;
-    let start = State::new(*boss_hp, *boss_damage);
+fn part2(boss: &Stats) -> i32 {
+    let mut battle = Battle::new(parse_spells(DEFAULT_SPELLS).unwrap(), PLAYER, *boss);
+    battle.bleed = 1;
 
-    play(start, true)
-}
-
-fn play(start: State, part_2: bool) -> i32 {
-    let mut todo = BinaryHeap::new();
-    let mut memoization = HashSet::new();
-
-    todo.push(Reverse((0, start)));
-    memoization.insert(start);
-
-    while let Some(Reverse((spent, mut state))) = todo.pop() {
-        state.apply_spell_effects();
-        if state.boss_dead() {
-            return spent;
-        }
-
-        if part_2 {
-            state.player_hp -= 1;
-            if state.player_hp <= 0 {
-                continue;
-            }
-        }
-
-        let mut state_copy = state;
-        if let Some((spell_cost, boss_dead)) = state_copy.magic_missile() {
-            if boss_dead {
-                return spent + spell_cost;
-            }
-
-            if state_copy.player_alive() && memoization.insert(state_copy) {
-                todo.push(Reverse((spent + spell_cost, state_copy)))
-            }
-        }
-
-        let mut state_copy = state;
-        if let Some((spell_cost, boss_dead)) = state_copy.drain() {
-            if boss_dead {
-                return spent + spell_cost;
-            }
-
-            if state_copy.player_alive() && memoization.insert(state_copy) {
-                todo.push(Reverse((spent + spell_cost, state_copy)))
-            }
-        }
-
-        let mut state_copy = state;
-        if let Some((spell_cost, boss_dead)) = state_copy.shield() {
-            if boss_dead {
-                return spent + spell_cost;
-            }
-
-            if state_copy.player_alive() && memoization.insert(state_copy) {
-                todo.push(Reverse((spent + spell_cost, state_copy)))
-            }
-        }
-
-        let mut state_copy = state;
-        if let Some((spell_cost, boss_dead)) = state_copy.poison() {
-            if boss_dead {
-                return spent + spell_cost;
-            }
-
-            if state_copy.player_alive() && memoization.insert(state_copy) {
-                todo.push(Reverse((spent + spell_cost, state_copy)))
-            }
-        }
-
-        let mut state_copy = state;
-        if let Some((spell_cost, boss_dead)) = state_copy.recharge() {
-            if boss_dead {
-                return spent + spell_cost;
-            }
-
-            if state_copy.player_alive() && memoization.insert(state_copy) {
-                todo.push(Reverse((spent + spell_cost, state_copy)))
-            }
-        }
-    }
-    unreachable!()
+    battle.min_mana().unwrap()
 }
 
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::rpg::{BattleLog, Turn};
 
     #[test]
     fn part1_example() {
@@ -248,4 +43,43 @@ Damage: 10";
 Damage: 10";
         assert_eq!(part2(&parse(input)), 1937);
     }
-}
\ No newline at end of file
+
+    fn replay(battle: &Battle, log: &BattleLog) -> (bool, i32) {
+        let mut state = battle.start();
+        let mut spent = 0;
+        for round in &log.rounds {
+            let Turn::Ongoing(turn) = battle.player_turn(&state) else { return (false, spent) };
+            spent += battle.spells[round.spell].cost;
+            match battle.cast(&turn, round.spell) {
+                Some(Turn::Ongoing(next)) | Some(Turn::Won(next)) => state = next,
+                _ => return (false, spent),
+            }
+            assert_eq!(state, round.state);
+        }
+        if !state.boss_dead() {
+            let Turn::Won(end) = battle.player_turn(&state) else { return (false, spent) };
+            state = end;
+        }
+        assert_eq!(state, log.end);
+        (true, spent)
+    }
+
+    #[test]
+    fn part1_battle_log() {
+        let battle = Battle::new(parse_spells(DEFAULT_SPELLS).unwrap(), PLAYER, parse("Hit Points: 71
+Damage: 10"));
+        let log = battle.best_battle().unwrap();
+        assert_eq!(replay(&battle, &log), (true, 1824));
+        assert_eq!(log.mana, 1824);
+    }
+
+    #[test]
+    fn part2_battle_log() {
+        let mut battle = Battle::new(parse_spells(DEFAULT_SPELLS).unwrap(), PLAYER, parse("Hit Points: 71
+Damage: 10"));
+        battle.bleed = 1;
+        let log = battle.best_battle().unwrap();
+        assert_eq!(replay(&battle, &log), (true, 1937));
+        assert_eq!(log.mana, 1937);
+    }
+}
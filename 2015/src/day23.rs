@@ -1,91 +1,116 @@
-use std::collections::HashMap;
+use std::fmt;
+
 use aoc_runner_derive::{aoc, aoc_generator};
 
+use crate::vm::{Halt, Instruction, Native, register, register_name, Registers, Vm};
+
 enum Op {
-    Hlf(char),
-    Tpl(char),
-    Inc(char),
-    Jmp(usize),
-    Jie(char, usize),
-    Jio(char, usize),
+    Hlf(usize),
+    Tpl(usize),
+    Inc(usize),
+    Jmp(isize),
+    Jie(usize, isize),
+    Jio(usize, isize),
+}
+
+impl Instruction for Op {
+    fn execute(&self, registers: &mut Registers) -> isize {
+        match *self {
+            Op::Hlf(reg) => registers[reg] /= 2,
+            Op::Tpl(reg) => registers[reg] *= 3,
+            Op::Inc(reg) => registers[reg] += 1,
+            Op::Jmp(offset) => return offset,
+            Op::Jie(reg, offset) => if registers[reg] % 2 == 0 { return offset },
+            Op::Jio(reg, offset) => if registers[reg] == 1 { return offset },
+        }
+        1
+    }
+
+    fn jump(&self) -> Option<isize> {
+        match *self {
+            Op::Jmp(offset) | Op::Jie(_, offset) | Op::Jio(_, offset) => Some(offset),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Op {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Op::Hlf(reg) => write!(f, "hlf {}", register_name(reg)),
+            Op::Tpl(reg) => write!(f, "tpl {}", register_name(reg)),
+            Op::Inc(reg) => write!(f, "inc {}", register_name(reg)),
+            Op::Jmp(offset) => write!(f, "jmp {offset:+}"),
+            Op::Jie(reg, offset) => write!(f, "jie {}, {offset:+}", register_name(reg)),
+            Op::Jio(reg, offset) => write!(f, "jio {}, {offset:+}", register_name(reg)),
+        }
+    }
 }
 
 #[aoc_generator(day23)]
 fn parse(input: &str) -> Vec<Op> {
     let mut result = vec![];
-    for (idx, line) in input.lines().enumerate() {
-        if let Some((first, offset)) = line.split_once(", ") {
-            let (instruction, register) = first.split_once(' ').unwrap();
-            let op = match instruction {
-                "jie" => Op::Jie(register.parse().unwrap(), idx.wrapping_add(offset.parse::<i32>().unwrap() as usize)),
-                "jio" => Op::Jio(register.parse().unwrap(), idx.wrapping_add(offset.parse::<i32>().unwrap() as usize)),
-                _ => unreachable!()
-            };
-            result.push(op);
-        } else {
-            let (instruction, register) = line.split_once(' ').unwrap();
-            let op = match instruction {
-                "hlf" => Op::Hlf(register.parse().unwrap()),
-                "tpl" => Op::Tpl(register.parse().unwrap()),
-                "inc" => Op::Inc(register.parse().unwrap()),
-                "jmp" => Op::Jmp(idx.wrapping_add(register.parse::<i32>().unwrap() as usize)),
-                _ => unreachable!()
-            };
-            result.push(op);
+    for line in input.lines() {
+        let (instruction, operands) = line.trim().split_once(' ').unwrap();
+        let offset = |value: &str| value.trim().parse::<isize>().unwrap();
+        let op = match (instruction, operands.split_once(',')) {
+            ("hlf", None) => Op::Hlf(register(operands).unwrap()),
+            ("tpl", None) => Op::Tpl(register(operands).unwrap()),
+            ("inc", None) => Op::Inc(register(operands).unwrap()),
+            ("jmp", None) => Op::Jmp(offset(operands)),
+            ("jie", Some((reg, value))) => Op::Jie(register(reg).unwrap(), offset(value)),
+            ("jio", Some((reg, value))) => Op::Jio(register(reg).unwrap(), offset(value)),
+            _ => panic!("Invalid instruction {line:?}")
+        };
+        result.push(op);
+    }
+    result
+}
+
+// The end of every program counts the steps of the Collatz sequence of `a` in `b`:
+//   jio a, +8 / inc b / jie a, +4 / tpl a / inc a / jmp +2 / hlf a / jmp -7
+fn collatz(program: &[Op]) -> Vec<Native> {
+    let mut result = vec![];
+    for (start, window) in program.windows(8).enumerate() {
+        let &[Op::Jio(n, 8), Op::Inc(count), Op::Jie(n1, 4), Op::Tpl(n2), Op::Inc(n3), Op::Jmp(2), Op::Hlf(n4), Op::Jmp(-7)] = window else {
+            continue;
+        };
+        if [n1, n2, n3, n4].iter().any(|&reg| reg != n) || count == n {
+            continue;
         }
+        let run = move |registers: &mut Registers| {
+            // Zero and negative numbers never reach 1, leave those to the budget of the VM
+            if registers[n] < 1 {
+                return false;
+            }
+            while registers[n] != 1 {
+                registers[count] += 1;
+                registers[n] = if registers[n] % 2 == 0 { registers[n] / 2 } else { 3 * registers[n] + 1 };
+            }
+            true
+        };
+        result.push(Native { start, end: start + 8, run: Box::new(run) });
     }
     result
 }
 
 #[aoc(day23, part1)]
-fn part1(input: &[Op]) -> usize {
-    let registers = HashMap::from([
-        ('a', 0),
-        ('b', 0)
-    ]);
-    run(input, registers)
+fn part1(input: &[Op]) -> i64 {
+    run(input, 0)
 }
 
 #[aoc(day23, part2)]
-fn part2(input: &[Op]) -> usize {
-    let registers = HashMap::from([
-        ('a', 1),
-        ('b', 0)
-    ]);
-    run(input, registers)
+fn part2(input: &[Op]) -> i64 {
+    run(input, 1)
 }
 
-fn run(input: &[Op], mut registers: HashMap<char, usize>) -> usize {
-    let mut pc = 0;
-
-    while pc < input.len() {
-        match input[pc] {
-            Op::Hlf(reg) => {
-                *registers.get_mut(&reg).unwrap() /= 2;
-                pc += 1;
-            },
-            Op::Tpl(reg) => {
-                *registers.get_mut(&reg).unwrap() *= 3;
-                pc += 1;
-            }
-            Op::Inc(reg) => {
-                *registers.get_mut(&reg).unwrap() += 1;
-                pc += 1;
-            }
-            Op::Jmp(index) => {
-                pc = index;
-            },
-            Op::Jie(reg, index) => {
-                let value = *registers.get_mut(&reg).unwrap();
-                pc = if value % 2 == 0 { index } else { pc + 1 };
-            },
-            Op::Jio(reg, index) => {
-                let value = *registers.get_mut(&reg).unwrap();
-                pc = if value == 1 { index } else { pc + 1 };
-            },
-        }
-    }
-    *registers.get_mut(&'b').unwrap()
+fn run(input: &[Op], a: i64) -> i64 {
+    let mut vm = Vm::new(input);
+    vm.natives = collatz(input);
+    vm.registers[0] = a;
+
+    assert_eq!(vm.run(), Halt::Finished);
+    vm.registers[1]
 }
 
 
@@ -149,4 +174,65 @@ jmp -7";
     fn part2_example() {
         assert_eq!(part2(&parse(INPUT)), 247);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn optimiser_matches_interpreter() {
+        let program = parse(INPUT);
+        let mut vm = Vm::new(&program);
+        vm.registers[0] = 1;
+        assert_eq!(vm.run(), Halt::Finished);
+
+        let mut optimised = Vm::new(&program);
+        optimised.natives = collatz(&program);
+        optimised.registers[0] = 1;
+        assert_eq!(optimised.run(), Halt::Finished);
+
+        assert_eq!(optimised.registers, vm.registers);
+        assert!(optimised.steps < vm.steps);
+    }
+
+    #[test]
+    fn budget_and_breakpoints() {
+        let program = parse("inc a
+jmp -1");
+        let mut vm = Vm::new(&program);
+        vm.budget = 10;
+        vm.breakpoints.insert(1);
+        vm.trace = Some(vec![]);
+
+        assert_eq!(vm.run(), Halt::Breakpoint(1));
+        assert_eq!(vm.run(), Halt::Breakpoint(1));
+        assert_eq!(vm.registers[0], 2);
+        vm.breakpoints.clear();
+        assert_eq!(vm.run(), Halt::OutOfBudget);
+        assert_eq!(vm.trace.unwrap().len(), 10);
+    }
+
+    #[test]
+    fn step_after_exit() {
+        // Jumping before the start ends the program, stepping further does nothing
+        let program = parse("inc a
+jmp -5");
+        let mut vm = Vm::new(&program);
+        vm.step();
+        vm.step();
+        assert!(vm.finished());
+        vm.step();
+        assert_eq!(vm.registers[0], 1);
+        assert_eq!(vm.steps, 2);
+        assert_eq!(vm.run(), Halt::Finished);
+    }
+
+    #[test]
+    fn disassembler() {
+        let program = parse("jio a, +2
+inc b
+jie a, -3
+hlf a");
+        assert_eq!(crate::vm::disassemble(&program), "    0: jio a, +2        -> 2
+    1: inc b
+>   2: jie a, -3        -> exit
+    3: hlf a
+");
+    }
+}
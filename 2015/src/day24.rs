@@ -1,5 +1,4 @@
 use aoc_runner_derive::{aoc, aoc_generator};
-use itertools::Itertools;
 
 #[aoc_generator(day24)]
 fn parse(input: &str) -> Vec<usize> {
@@ -9,35 +8,93 @@ fn parse(input: &str) -> Vec<usize> {
 }
 
 #[aoc(day24, part1)]
-fn part1(input: &[usize]) -> usize {
-    let target = input.iter().sum::<usize>() / 3;
-
-    solve(input, target)
+fn part1(input: &[usize]) -> u128 {
+    entanglement(&balance(input, 3).unwrap()[0])
 }
 
 #[aoc(day24, part2)]
-fn part2(input: &[usize]) -> usize {
-    let target = input.iter().sum::<usize>() / 4;
+fn part2(input: &[usize]) -> u128 {
+    entanglement(&balance(input, 4).unwrap()[0])
+}
+
+fn entanglement(group: &[usize]) -> u128 {
+    group.iter().map(|&package| package as u128).product()
+}
+
+// Splits the packages in groups of equal weight, the first group is the passenger compartment.
+// It has the fewest packages and the smallest quantum entanglement among all valid groupings.
+fn balance(packages: &[usize], compartments: usize) -> Option<Vec<Vec<usize>>> {
+    let total: usize = packages.iter().sum();
+    if compartments == 0 || !total.is_multiple_of(compartments) {
+        return None;
+    }
+    let target = total / compartments;
+
+    let mut sorted = packages.to_vec();
+    sorted.sort_unstable();
+
+    for size in 1..=sorted.len() {
+        let mut candidates = vec![];
+        subsets(&sorted, size, target, 0, &mut vec![], &mut candidates);
+        candidates.sort_by_cached_key(|group: &Vec<usize>| {
+            group.iter().map(|&idx| sorted[idx] as u128).product::<u128>()
+        });
+
+        for group in candidates {
+            // Largest packages first fails faster when the rest can't be split
+            let rest: Vec<usize> = (0..sorted.len()).rev()
+                .filter(|idx| !group.contains(idx))
+                .map(|idx| sorted[idx])
+                .collect();
+            let mut bins = vec![vec![]; compartments - 1];
+            let mut sums = vec![0; compartments - 1];
+
+            if fill(&rest, &mut bins, &mut sums, target) {
+                let mut result = vec![group.iter().map(|&idx| sorted[idx]).collect()];
+                result.extend(bins);
+                return Some(result);
+            }
+        }
+    }
+    None
+}
 
-    solve(input, target)
+// Indices of all groups with `size` packages that weigh exactly `target`, packages are sorted ascending
+fn subsets(packages: &[usize], size: usize, target: usize, start: usize, current: &mut Vec<usize>, result: &mut Vec<Vec<usize>>) {
+    if current.len() == size {
+        if target == 0 {
+            result.push(current.clone());
+        }
+        return;
+    }
+    for idx in start..packages.len() {
+        if packages[idx] > target || packages.len() - idx < size - current.len() {
+            break;
+        }
+        current.push(idx);
+        subsets(packages, size, target - packages[idx], idx + 1, current, result);
+        current.pop();
+    }
 }
 
-fn solve(input: &[usize], target: usize) -> usize {
-    for n in 0..input.len() {
-        // Get combinations with min amount of packages that is equal to target
-        // This is for the passenger compartment
-        // It is trivial to divide the remaining items equally in weight over the 2 or 3 other compartments
-        let combs: Vec<Vec<&usize>> = input.iter().combinations(n)
-            .filter(|comb| comb.iter().cloned().sum::<usize>() == target).collect();
-
-        if !combs.is_empty() {
-            // Search comb with smallest quantum entanglement
-            return combs.iter()
-                .map(|comb| comb.iter().copied().product::<usize>())
-                .min().unwrap()
+// k-partition search, bins with the same weight are interchangeable so only one of them is tried
+fn fill(packages: &[usize], bins: &mut [Vec<usize>], sums: &mut [usize], target: usize) -> bool {
+    let Some((&package, rest)) = packages.split_first() else {
+        return sums.iter().all(|&sum| sum == target);
+    };
+    for bin in 0..bins.len() {
+        if sums[bin] + package > target || sums[..bin].contains(&sums[bin]) {
+            continue;
         }
+        sums[bin] += package;
+        bins[bin].push(package);
+        if fill(rest, bins, sums, target) {
+            return true;
+        }
+        sums[bin] -= package;
+        bins[bin].pop();
     }
-    unreachable!()
+    false
 }
 
 
@@ -84,4 +141,23 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&parse(INPUT)), 74850409);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn grouping_example() {
+        let packages = [1, 2, 3, 4, 5, 7, 8, 9, 10, 11];
+        assert_eq!(balance(&packages, 3).unwrap()[0], vec![9, 11]);
+        assert_eq!(balance(&packages, 4).unwrap()[0], vec![4, 11]);
+
+        let groups = balance(&packages, 4).unwrap();
+        assert!(groups.iter().all(|group| group.iter().sum::<usize>() == 15));
+        assert_eq!(groups.iter().map(Vec::len).sum::<usize>(), packages.len());
+    }
+
+    #[test]
+    fn first_group_must_leave_a_valid_split() {
+        // 1 + 10 + 19 is the smallest first group, but 2, 3, 9, 14, 15 and 17 can't be split in two
+        let packages = parse("1\n2\n3\n9\n10\n14\n15\n17\n19");
+        assert_eq!(part1(&packages), 210);
+        assert_eq!(balance(&[1, 4, 7, 8, 13, 16, 17, 18], 3), None);
+    }
+}
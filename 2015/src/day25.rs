@@ -1,8 +1,14 @@
+use std::collections::HashMap;
+
 use aoc_runner_derive::{aoc, aoc_generator};
 use regex::Regex;
 
+const FIRST_CODE: u64 = 20151125;
+const MULTIPLIER: u64 = 252533;
+const MODULUS: u64 = 33554393;
+
 #[aoc_generator(day25)]
-fn parse(input: &str) -> (usize, usize) {
+fn parse(input: &str) -> (u64, u64) {
     let re = Regex::new(r"-?\d+").unwrap();
     let matches: Vec<_> = re.find_iter(input).map(|m| m.as_str().parse().unwrap()).collect();
     assert_eq!(matches.len(), 2);
@@ -10,33 +16,108 @@ fn parse(input: &str) -> (usize, usize) {
 }
 
 #[aoc(day25, part1)]
-fn part1(input: &(usize, usize)) -> usize {
-    let (row, column) = input;
+fn part1(input: &(u64, u64)) -> u64 {
+    let (row, column) = *input;
+
+    code_at(row, column)
+}
 
+#[aoc(day25, part2)]
+fn part2(_input: &(u64, u64)) -> String {
+    String::from("n/a")
+}
+
+// Codes are filled in diagonally, (1, 1) has index 0
+pub fn index(row: u64, column: u64) -> u64 {
     let n = column + row - 1;
     let triangle = (n * (n + 1)) / 2;
-    let index = triangle - row;
+    triangle - row
+}
+
+pub fn position(index: u64) -> (u64, u64) {
+    // Diagonal n holds the indices from triangle(n - 1) up to triangle(n)
+    let mut n = ((2.0 * index as f64).sqrt() as u64).max(1);
+    while n * (n + 1) / 2 <= index {
+        n += 1;
+    }
+    while n > 1 && (n - 1) * n / 2 > index {
+        n -= 1;
+    }
+    let row = n * (n + 1) / 2 - index;
+    (row, n + 1 - row)
+}
 
+pub fn code_at(row: u64, column: u64) -> u64 {
     // https://en.wikipedia.org/wiki/Modular_exponentiation
-    (20151125 * modular_pow(252533, index, 33554393)) % 33554393
+    mul_mod(FIRST_CODE, modular_pow(MULTIPLIER, index(row, column), MODULUS), MODULUS)
 }
 
-#[aoc(day25, part2)]
-fn part2(_input: &(usize, usize)) -> String {
-    String::from("n/a")
+// Smallest index that produces the code together with its position in the grid
+pub fn find_code(code: u64) -> Option<(u64, (u64, u64))> {
+    let target = mul_mod(code, modular_inverse(FIRST_CODE, MODULUS)?, MODULUS);
+    let index = discrete_log(MULTIPLIER, target, MODULUS)?;
+    Some((index, position(index)))
+}
+
+fn mul_mod(a: u64, b: u64, modulus: u64) -> u64 {
+    ((a as u128 * b as u128) % modulus as u128) as u64
 }
 
-fn modular_pow(base: usize, exponent: usize, modulus: usize) -> usize {
+fn modular_pow(base: u64, mut exponent: u64, modulus: u64) -> u64 {
     if modulus == 1 {
         return 0;
     }
+    let mut base = base % modulus;
     let mut c = 1;
-    for _ in 0..exponent {
-        c = (c * base) % modulus
+    while exponent > 0 {
+        if exponent & 1 == 1 {
+            c = mul_mod(c, base, modulus);
+        }
+        base = mul_mod(base, base, modulus);
+        exponent >>= 1;
     }
     c
 }
 
+fn modular_inverse(a: u64, modulus: u64) -> Option<u64> {
+    // Extended Euclid
+    let (mut old_r, mut r) = (a as i128, modulus as i128);
+    let (mut old_s, mut s) = (1i128, 0i128);
+    while r != 0 {
+        let q = old_r / r;
+        (old_r, r) = (r, old_r - q * r);
+        (old_s, s) = (s, old_s - q * s);
+    }
+    if old_r != 1 {
+        return None;
+    }
+    Some(old_s.rem_euclid(modulus as i128) as u64)
+}
+
+// Baby-step giant-step: smallest x with base^x = target (mod modulus)
+// https://en.wikipedia.org/wiki/Baby-step_giant-step
+fn discrete_log(base: u64, target: u64, modulus: u64) -> Option<u64> {
+    let m = (modulus as f64).sqrt().ceil() as u64;
+    let target = target % modulus;
+
+    let mut baby_steps = HashMap::new();
+    let mut value = 1;
+    for j in 0..m {
+        baby_steps.entry(value).or_insert(j);
+        value = mul_mod(value, base, modulus);
+    }
+
+    let giant_step = modular_inverse(modular_pow(base, m, modulus), modulus)?;
+    let mut gamma = target;
+    for i in 0..m {
+        if let Some(j) = baby_steps.get(&gamma) {
+            return Some(i * m + j);
+        }
+        gamma = mul_mod(gamma, giant_step, modulus);
+    }
+    None
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -50,4 +131,46 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&parse("Enter the code at row 3010, column 3019.")), "n/a");
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn grid_example() {
+        assert_eq!(code_at(1, 1), 20151125);
+        assert_eq!(code_at(2, 1), 31916031);
+        assert_eq!(code_at(1, 6), 33511524);
+        assert_eq!(code_at(6, 6), 27995004);
+    }
+
+    #[test]
+    fn find_code_example() {
+        assert_eq!(find_code(20151125), Some((0, (1, 1))));
+        assert_eq!(find_code(31916031), Some((1, (2, 1))));
+        assert_eq!(find_code(27995004), Some((index(6, 6), (6, 6))));
+        assert_eq!(find_code(0), None);
+    }
+
+    #[test]
+    fn round_trip() {
+        for (row, column) in [(1, 1), (4, 2), (1000, 2), (1, 5000), (2000, 2000)] {
+            assert_eq!(position(index(row, column)), (row, column));
+            assert_eq!(find_code(code_at(row, column)), Some((index(row, column), (row, column))));
+        }
+    }
+
+    #[test]
+    fn codes_repeat() {
+        // The multiplier has order (MODULUS - 1) / 2, so later codes were already seen closer to the origin
+        let (found, (row, column)) = find_code(8997277).unwrap();
+        assert_eq!(found + (MODULUS - 1) / 2, index(3010, 3019));
+        assert_eq!(code_at(row, column), 8997277);
+        assert_eq!(position(index(3010, 3019)), (3010, 3019));
+    }
+
+    #[test]
+    fn fast_pow() {
+        let mut c = 1;
+        for exponent in 0..1000 {
+            assert_eq!(modular_pow(MULTIPLIER, exponent, MODULUS), c);
+            c = (c * MULTIPLIER) % MODULUS;
+        }
+    }
+}
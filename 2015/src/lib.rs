@@ -30,4 +30,7 @@ pub mod day23;
 pub mod day24;
 pub mod day25;
 
+pub mod rpg;
+pub mod vm;
+
 aoc_lib!{ year = 2015 }
\ No newline at end of file
@@ -0,0 +1,560 @@
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashSet};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::str::FromStr;
+
+pub const DEFAULT_SHOP: &str = "Weapons:    Cost  Damage  Armor
+Dagger        8     4       0
+Shortsword   10     5       0
+Warhammer    25     6       0
+Longsword    40     7       0
+Greataxe     74     8       0
+
+Armor:      Cost  Damage  Armor
+Leather      13     0       1
+Chainmail    31     0       2
+Splintmail   53     0       3
+Bandedmail   75     0       4
+Platemail   102     0       5
+
+Rings:      Cost  Damage  Armor
+Damage +1    25     1       0
+Damage +2    50     2       0
+Damage +3   100     3       0
+Defense +1   20     0       1
+Defense +2   40     0       2
+Defense +3   80     0       3";
+
+// Stats happen once when the spell is cast, stats ending in "per turn" every turn while the effect is active
+pub const DEFAULT_SPELLS: &str = "Magic Missile: cost 53, damage 4
+Drain: cost 73, damage 2, heal 2
+Shield: cost 113, turns 6, armor 7 per turn
+Poison: cost 173, turns 6, damage 3 per turn
+Recharge: cost 229, turns 5, mana 101 per turn";
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Stats {
+    pub hp: i32,
+    pub damage: i32,
+    pub armor: i32,
+    pub mana: i32,
+}
+
+impl FromStr for Stats {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut stats = Stats::default();
+        for line in input.lines().filter(|line| !line.trim().is_empty()) {
+            let (stat, value) = line.split_once(':').ok_or(format!("Missing ':' in {line:?}"))?;
+            let value = value.trim().parse().map_err(|_| format!("Invalid value in {line:?}"))?;
+            match stat.trim() {
+                "Hit Points" => stats.hp = value,
+                "Damage" => stats.damage = value,
+                "Armor" => stats.armor = value,
+                "Mana" => stats.mana = value,
+                _ => return Err(format!("Unknown stat {stat:?}")),
+            }
+        }
+        Ok(stats)
+    }
+}
+
+fn attack(damage: i32, armor: i32) -> i32 {
+    (damage - armor).max(1)
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Item {
+    pub name: String,
+    pub cost: u32,
+    pub damage: i32,
+    pub armor: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Loadout {
+    pub items: Vec<String>,
+    pub cost: u32,
+    pub damage: i32,
+    pub armor: i32,
+}
+
+impl Loadout {
+    fn with(&self, item: &Item) -> Self {
+        let mut items = self.items.clone();
+        items.push(item.name.clone());
+        Loadout {
+            items,
+            cost: self.cost + item.cost,
+            damage: self.damage + item.damage,
+            armor: self.armor + item.armor,
+        }
+    }
+
+    pub fn equip(&self, stats: Stats) -> Stats {
+        Stats {
+            damage: stats.damage + self.damage,
+            armor: stats.armor + self.armor,
+            ..stats
+        }
+    }
+}
+
+// One weapon, at most one armor and at most two different rings
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Shop {
+    pub weapons: Vec<Item>,
+    pub armor: Vec<Item>,
+    pub rings: Vec<Item>,
+}
+
+impl Default for Shop {
+    fn default() -> Self {
+        DEFAULT_SHOP.parse().unwrap()
+    }
+}
+
+impl FromStr for Shop {
+    type Err = String;
+
+    fn from_str(input: &str) -> Result<Self, Self::Err> {
+        let mut shop = Shop { weapons: vec![], armor: vec![], rings: vec![] };
+        for section in input.split("\n\n") {
+            let mut lines = section.trim().lines();
+            let header = lines.next().ok_or("Empty shop section")?;
+            let (category, _) = header.split_once(':').ok_or(format!("Missing ':' in {header:?}"))?;
+            let items = match category.trim() {
+                "Weapons" => &mut shop.weapons,
+                "Armor" => &mut shop.armor,
+                "Rings" => &mut shop.rings,
+                _ => return Err(format!("Unknown shop category {category:?}")),
+            };
+            for line in lines {
+                // Names can contain spaces, the last three columns are always numbers
+                let mut columns = line.split_whitespace().rev();
+                let mut number = || columns.next()
+                    .and_then(|value| value.parse().ok())
+                    .ok_or(format!("Invalid shop item {line:?}"));
+                let armor = number()?;
+                let damage = number()?;
+                let cost = number()? as u32;
+                let name = columns.rev().collect::<Vec<_>>().join(" ");
+                items.push(Item { name, cost, damage, armor });
+            }
+        }
+        Ok(shop)
+    }
+}
+
+impl Shop {
+    pub fn from_file(path: impl AsRef<Path>) -> Result<Self, String> {
+        fs::read_to_string(path).map_err(|e| e.to_string())?.parse()
+    }
+
+    pub fn loadouts(&self) -> Vec<Loadout> {
+        let empty = Loadout { items: vec![], cost: 0, damage: 0, armor: 0 };
+        let mut result = vec![];
+
+        for weapon in &self.weapons {
+            let weapon = empty.with(weapon);
+            let mut protected = vec![weapon.clone()];
+            protected.extend(self.armor.iter().map(|armor| weapon.with(armor)));
+
+            for loadout in protected {
+                result.push(loadout.clone());
+                for (i, ring) in self.rings.iter().enumerate() {
+                    let one = loadout.with(ring);
+                    for other in &self.rings[i + 1..] {
+                        result.push(one.with(other));
+                    }
+                    result.push(one);
+                }
+            }
+        }
+        result
+    }
+
+    pub fn cheapest_win(&self, player: Stats, boss: Stats) -> Option<Loadout> {
+        self.loadouts().into_iter()
+            .filter(|loadout| duel(loadout.equip(player), boss))
+            .min_by_key(|loadout| loadout.cost)
+    }
+
+    pub fn priciest_loss(&self, player: Stats, boss: Stats) -> Option<Loadout> {
+        self.loadouts().into_iter()
+            .filter(|loadout| !duel(loadout.equip(player), boss))
+            .max_by_key(|loadout| loadout.cost)
+    }
+}
+
+// Physical fight where the player attacks first, returns whether the player wins
+pub fn duel(player: Stats, boss: Stats) -> bool {
+    let (mut player_hp, mut boss_hp) = (player.hp, boss.hp);
+    loop {
+        boss_hp -= attack(player.damage, boss.armor);
+        if boss_hp <= 0 {
+            return true;
+        }
+        player_hp -= attack(boss.damage, player.armor);
+        if player_hp <= 0 {
+            return false;
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct Effect {
+    pub damage: i32,
+    pub heal: i32,
+    pub armor: i32,
+    pub mana: i32,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Spell {
+    pub name: String,
+    pub cost: i32,
+    pub immediate: Effect,
+    pub turns: u8,
+    pub effect: Effect,
+}
+
+impl FromStr for Spell {
+    type Err = String;
+
+    // Stats apply once when the spell is cast, unless they end in "per turn", then they apply every turn of the effect
+    fn from_str(line: &str) -> Result<Self, Self::Err> {
+        let (name, fields) = line.split_once(':').ok_or(format!("Missing ':' in {line:?}"))?;
+        let mut spell = Spell {
+            name: name.trim().to_string(),
+            cost: 0,
+            immediate: Effect::default(),
+            turns: 0,
+            effect: Effect::default(),
+        };
+        for field in fields.split(',') {
+            let (key, value) = field.trim().split_once(' ').ok_or(format!("Invalid field {field:?}"))?;
+            let (value, per_turn) = match value.trim().strip_suffix("per turn") {
+                Some(value) => (value, true),
+                None => (value, false),
+            };
+            let effect = if per_turn { &mut spell.effect } else { &mut spell.immediate };
+            let value: i32 = value.trim().parse().map_err(|_| format!("Invalid value in {field:?}"))?;
+            match key {
+                "cost" | "turns" if per_turn => return Err(format!("{key} can't be per turn in {field:?}")),
+                "cost" => spell.cost = value,
+                "turns" => spell.turns = u8::try_from(value).map_err(|_| format!("Invalid amount of turns in {field:?}"))?,
+                "damage" => effect.damage = value,
+                "heal" => effect.heal = value,
+                "armor" => effect.armor = value,
+                "mana" => effect.mana = value,
+                _ => return Err(format!("Unknown spell field {key:?}")),
+            }
+        }
+        if spell.turns == 0 && spell.effect != Effect::default() {
+            return Err(format!("{} has an effect per turn but no turns", spell.name));
+        }
+        Ok(spell)
+    }
+}
+
+pub fn parse_spells(input: &str) -> Result<Vec<Spell>, String> {
+    input.lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::parse)
+        .collect()
+}
+
+pub fn load_spells(path: impl AsRef<Path>) -> Result<Vec<Spell>, String> {
+    parse_spells(&fs::read_to_string(path).map_err(|e| e.to_string())?)
+}
+
+// Timers hold the remaining turns of every spell effect, indexed like the spells of the battle
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct State {
+    pub player_hp: i32,
+    pub player_mana: i32,
+    pub boss_hp: i32,
+    pub timers: Vec<u8>,
+}
+
+impl State {
+    pub fn player_alive(&self) -> bool {
+        self.player_hp > 0
+    }
+
+    pub fn boss_dead(&self) -> bool {
+        self.boss_hp <= 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Turn {
+    Won(State),
+    Lost(State),
+    Ongoing(State),
+}
+
+pub struct Battle {
+    pub spells: Vec<Spell>,
+    pub player: Stats,
+    pub boss: Stats,
+    // Hit points the player loses at the start of each of their turns (hard mode)
+    pub bleed: i32,
+}
+
+impl Battle {
+    pub fn new(spells: Vec<Spell>, player: Stats, boss: Stats) -> Self {
+        Battle { spells, player, boss, bleed: 0 }
+    }
+
+    pub fn start(&self) -> State {
+        State {
+            player_hp: self.player.hp,
+            player_mana: self.player.mana,
+            boss_hp: self.boss.hp,
+            timers: vec![0; self.spells.len()],
+        }
+    }
+
+    fn apply_effects(&self, state: &mut State) {
+        for (spell, timer) in self.spells.iter().zip(state.timers.iter_mut()) {
+            if *timer > 0 {
+                *timer -= 1;
+                state.boss_hp -= spell.effect.damage;
+                state.player_hp += spell.effect.heal;
+                state.player_mana += spell.effect.mana;
+            }
+        }
+    }
+
+    // Armor effects last while their timer hasn't run out after the effects of the turn are applied
+    pub fn armor(&self, state: &State) -> i32 {
+        self.player.armor + self.spells.iter().zip(&state.timers)
+            .filter(|(_, &timer)| timer > 0)
+            .map(|(spell, _)| spell.effect.armor)
+            .sum::<i32>()
+    }
+
+    pub fn can_cast(&self, state: &State, spell: usize) -> bool {
+        state.player_mana >= self.spells[spell].cost && state.timers[spell] == 0
+    }
+
+    // Start of the player turn, before a spell is chosen
+    pub fn player_turn(&self, state: &State) -> Turn {
+        let mut state = state.clone();
+        state.player_hp -= self.bleed;
+        if !state.player_alive() {
+            return Turn::Lost(state);
+        }
+        self.apply_effects(&mut state);
+        if state.boss_dead() {
+            return Turn::Won(state);
+        }
+        Turn::Ongoing(state)
+    }
+
+    // Casts a spell and plays the boss turn, `None` when the spell can't be cast
+    pub fn cast(&self, state: &State, spell: usize) -> Option<Turn> {
+        if !self.can_cast(state, spell) {
+            return None;
+        }
+        let mut state = state.clone();
+        let Spell { cost, immediate, turns, .. } = &self.spells[spell];
+        state.player_mana -= cost;
+        state.boss_hp -= immediate.damage;
+        state.player_hp += immediate.heal;
+        state.player_mana += immediate.mana;
+        state.timers[spell] = *turns;
+        if state.boss_dead() {
+            return Some(Turn::Won(state));
+        }
+
+        self.apply_effects(&mut state);
+        if state.boss_dead() {
+            return Some(Turn::Won(state));
+        }
+        state.player_hp -= attack(self.boss.damage, self.armor(&state));
+        if !state.player_alive() {
+            return Some(Turn::Lost(state));
+        }
+        Some(Turn::Ongoing(state))
+    }
+
+    pub fn min_mana(&self) -> Option<i32> {
+        self.best_battle().map(|log| log.mana)
+    }
+
+    // Dijkstra over battle states at the start of the player turn, weighted by mana spent.
+    // Every node keeps the round that led to it so the winning battle can be replayed.
+    pub fn best_battle(&self) -> Option<BattleLog> {
+        let mut nodes: Vec<(Option<usize>, usize, State)> = vec![(None, 0, self.start())];
+        let mut todo = BinaryHeap::new();
+        let mut seen = HashSet::new();
+        let mut best: Option<(i32, usize, State)> = None;
+
+        todo.push(Reverse((0, 0)));
+        while let Some(Reverse((spent, node))) = todo.pop() {
+            if best.as_ref().is_some_and(|(best, _, _)| spent >= *best) {
+                break;
+            }
+            if !seen.insert(nodes[node].2.clone()) {
+                continue;
+            }
+            let state = match self.player_turn(&nodes[node].2) {
+                Turn::Won(end) => {
+                    best = Some((spent, node, end));
+                    break;
+                },
+                Turn::Lost(_) => continue,
+                Turn::Ongoing(state) => state,
+            };
+
+            for spell in 0..self.spells.len() {
+                let cost = spent + self.spells[spell].cost;
+                match self.cast(&state, spell) {
+                    Some(Turn::Won(end)) if best.as_ref().is_none_or(|(best, _, _)| cost < *best) => {
+                        nodes.push((Some(node), spell, end.clone()));
+                        best = Some((cost, nodes.len() - 1, end));
+                    },
+                    Some(Turn::Ongoing(next)) => {
+                        nodes.push((Some(node), spell, next));
+                        todo.push(Reverse((cost, nodes.len() - 1)));
+                    },
+                    _ => {}
+                }
+            }
+        }
+
+        let (mana, mut node, end) = best?;
+        let mut rounds = vec![];
+        while let (Some(parent), spell, state) = &nodes[node] {
+            rounds.push(Round { spell: *spell, state: state.clone() });
+            node = *parent;
+        }
+        rounds.reverse();
+
+        Some(BattleLog {
+            names: self.spells.iter().map(|spell| spell.name.clone()).collect(),
+            start: self.start(),
+            rounds,
+            end,
+            mana,
+        })
+    }
+}
+
+// A player turn with the spell cast and the state once the boss turn is over (or the boss died)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Round {
+    pub spell: usize,
+    pub state: State,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BattleLog {
+    pub names: Vec<String>,
+    pub start: State,
+    pub rounds: Vec<Round>,
+    pub end: State,
+    pub mana: i32,
+}
+
+impl BattleLog {
+    fn describe(&self, state: &State) -> String {
+        let mut description = format!("player {} hp, {} mana, boss {} hp", state.player_hp, state.player_mana, state.boss_hp);
+        let effects: Vec<String> = self.names.iter().zip(&state.timers)
+            .filter(|(_, &timer)| timer > 0)
+            .map(|(name, timer)| format!("{name} ({timer})"))
+            .collect();
+        if !effects.is_empty() {
+            description += &format!(", effects: {}", effects.join(", "));
+        }
+        description
+    }
+}
+
+impl fmt::Display for BattleLog {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Start: {}", self.describe(&self.start))?;
+        for (turn, round) in self.rounds.iter().enumerate() {
+            writeln!(f, "Round {}: cast {} -> {}", turn + 1, self.names[round.spell], self.describe(&round.state))?;
+        }
+        writeln!(f, "End: {}", self.describe(&self.end))?;
+        write!(f, "Boss defeated with {} mana spent", self.mana)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_shop() {
+        let shop = Shop::default();
+        assert_eq!(shop.weapons.len(), 5);
+        assert_eq!(shop.armor.len(), 5);
+        assert_eq!(shop.rings[3], Item { name: String::from("Defense +1"), cost: 20, damage: 0, armor: 1 });
+        // 5 weapons * 6 armor choices * 22 ring choices
+        assert_eq!(shop.loadouts().len(), 660);
+    }
+
+    #[test]
+    fn duel_example() {
+        let player = Stats { hp: 8, damage: 5, armor: 5, mana: 0 };
+        let boss = Stats { hp: 12, damage: 7, armor: 2, mana: 0 };
+        assert!(duel(player, boss));
+    }
+
+    #[test]
+    fn default_spells() {
+        let spells = parse_spells(DEFAULT_SPELLS).unwrap();
+        assert_eq!(spells[1].immediate, Effect { damage: 2, heal: 2, armor: 0, mana: 0 });
+        assert_eq!(spells[2].turns, 6);
+        assert_eq!(spells[2].effect, Effect { damage: 0, heal: 0, armor: 7, mana: 0 });
+        assert!(parse_spells("Fireball: cost 10, burn 3").is_err());
+
+        // Field order doesn't matter, only the "per turn" marker does
+        let spell: Spell = "Blaze: damage 5, cost 80, damage 1 per turn, turns 3".parse().unwrap();
+        assert_eq!(spell.immediate, Effect { damage: 5, ..Effect::default() });
+        assert_eq!(spell.effect, Effect { damage: 1, ..Effect::default() });
+        assert_eq!(spell.turns, 3);
+        assert!("Ember: cost 10, damage 1 per turn".parse::<Spell>().is_err());
+        assert!("Eternal: cost 10, turns 256, armor 1 per turn".parse::<Spell>().is_err());
+        assert!("Backwards: cost 10, turns -1, armor 1 per turn".parse::<Spell>().is_err());
+        assert!("Upkeep: cost 5 per turn, turns 3, armor 1 per turn".parse::<Spell>().is_err());
+        assert!("Lasting: cost 5, turns 3 per turn, armor 1 per turn".parse::<Spell>().is_err());
+    }
+
+    #[test]
+    fn battle_example1() {
+        let player = Stats { hp: 10, mana: 250, ..Stats::default() };
+        let boss = Stats { hp: 13, damage: 8, ..Stats::default() };
+        let battle = Battle::new(parse_spells(DEFAULT_SPELLS).unwrap(), player, boss);
+
+        let Turn::Ongoing(state) = battle.player_turn(&battle.start()) else { panic!() };
+        let Some(Turn::Ongoing(state)) = battle.cast(&state, 3) else { panic!() };
+        assert_eq!((state.player_hp, state.player_mana, state.boss_hp), (2, 77, 10));
+        let Turn::Ongoing(state) = battle.player_turn(&state) else { panic!() };
+        assert_eq!(battle.cast(&state, 0).map(|turn| matches!(turn, Turn::Won(_))), Some(true));
+
+        assert_eq!(battle.min_mana(), Some(226));
+    }
+
+    #[test]
+    fn battle_log_example1() {
+        let player = Stats { hp: 10, mana: 250, ..Stats::default() };
+        let boss = Stats { hp: 13, damage: 8, ..Stats::default() };
+        let battle = Battle::new(parse_spells(DEFAULT_SPELLS).unwrap(), player, boss);
+
+        let log = battle.best_battle().unwrap();
+        assert_eq!(log.to_string(), "Start: player 10 hp, 250 mana, boss 13 hp
+Round 1: cast Poison -> player 2 hp, 77 mana, boss 10 hp, effects: Poison (5)
+Round 2: cast Magic Missile -> player 2 hp, 24 mana, boss 0 hp, effects: Poison (3)
+End: player 2 hp, 24 mana, boss 0 hp, effects: Poison (3)
+Boss defeated with 226 mana spent");
+    }
+}
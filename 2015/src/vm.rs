@@ -0,0 +1,142 @@
+use std::collections::HashSet;
+use std::fmt;
+
+pub const REGISTERS: usize = 26;
+
+pub type Registers = [i64; REGISTERS];
+
+// Registers are named 'a' to 'z'
+pub fn register(name: &str) -> Result<usize, String> {
+    match name.trim().as_bytes() {
+        &[ch @ b'a'..=b'z'] => Ok((ch - b'a') as usize),
+        _ => Err(format!("Invalid register {name:?}")),
+    }
+}
+
+pub fn register_name(register: usize) -> char {
+    (b'a' + register as u8) as char
+}
+
+pub trait Instruction: fmt::Display {
+    // Executes the instruction and returns the offset of the next instruction
+    fn execute(&self, registers: &mut Registers) -> isize;
+
+    // Offset of the instruction this can jump to, used by the disassembler
+    fn jump(&self) -> Option<isize> {
+        None
+    }
+}
+
+// Runs `start..end` of the program natively whenever the VM arrives at `start`.
+// The native code can refuse (return false) and the VM then interprets the instructions instead.
+pub struct Native {
+    pub start: usize,
+    pub end: usize,
+    pub run: Box<dyn Fn(&mut Registers) -> bool>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Halt {
+    Finished,
+    Breakpoint(usize),
+    OutOfBudget,
+}
+
+pub struct Vm<'a, I: Instruction> {
+    program: &'a [I],
+    pub registers: Registers,
+    pub pc: usize,
+    pub steps: u64,
+    pub budget: u64,
+    pub breakpoints: HashSet<usize>,
+    pub natives: Vec<Native>,
+    // Executed pc and the registers afterwards, only recorded when enabled
+    pub trace: Option<Vec<(usize, Registers)>>,
+}
+
+impl<'a, I: Instruction> Vm<'a, I> {
+    pub fn new(program: &'a [I]) -> Self {
+        Vm {
+            program,
+            registers: [0; REGISTERS],
+            pc: 0,
+            steps: 0,
+            budget: 100_000_000,
+            breakpoints: HashSet::new(),
+            natives: vec![],
+            trace: None,
+        }
+    }
+
+    pub fn finished(&self) -> bool {
+        self.pc >= self.program.len()
+    }
+
+    // Executes a single instruction or native block, a finished program stays finished
+    pub fn step(&mut self) {
+        if self.finished() {
+            return;
+        }
+        let native = self.natives.iter().find(|native| native.start == self.pc);
+        if let Some(native) = native {
+            if (native.run)(&mut self.registers) {
+                self.pc = native.end;
+                self.record(native.start);
+                return;
+            }
+        }
+
+        let pc = self.pc;
+        let offset = self.program[pc].execute(&mut self.registers);
+        // Jumping before the start of the program ends it just like jumping past the end
+        self.pc = pc.checked_add_signed(offset).unwrap_or(usize::MAX);
+        self.record(pc);
+    }
+
+    fn record(&mut self, pc: usize) {
+        self.steps += 1;
+        if let Some(trace) = &mut self.trace {
+            trace.push((pc, self.registers));
+        }
+    }
+
+    // Runs until the program ends, a breakpoint is reached or the budget of steps is spent.
+    // A breakpoint on the current pc doesn't stop the VM so a run can be resumed.
+    pub fn run(&mut self) -> Halt {
+        let mut first = true;
+        loop {
+            if self.finished() {
+                return Halt::Finished;
+            }
+            if !first && self.breakpoints.contains(&self.pc) {
+                return Halt::Breakpoint(self.pc);
+            }
+            if self.steps >= self.budget {
+                return Halt::OutOfBudget;
+            }
+            self.step();
+            first = false;
+        }
+    }
+}
+
+// Lists the program with resolved jump targets, instructions that can be jumped to are marked with '>'
+pub fn disassemble<I: Instruction>(program: &[I]) -> String {
+    let targets: Vec<Option<usize>> = program.iter().enumerate()
+        .map(|(pc, instruction)| instruction.jump().and_then(|offset| pc.checked_add_signed(offset)))
+        .collect();
+
+    let mut result = String::new();
+    for (pc, instruction) in program.iter().enumerate() {
+        let marker = if targets.contains(&Some(pc)) { '>' } else { ' ' };
+        let line = format!("{marker}{pc:>4}: {instruction}");
+        let line = match (instruction.jump(), targets[pc]) {
+            (None, _) => line,
+            (Some(_), Some(target)) if target < program.len() => format!("{line:<24}-> {target}"),
+            (Some(_), _) => format!("{line:<24}-> exit"),
+        };
+        result.push_str(line.trim_end());
+        result.push('\n');
+    }
+    result
+}
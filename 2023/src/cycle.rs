@@ -0,0 +1,135 @@
+use std::collections::hash_map::Entry;
+use std::hash::Hash;
+
+use rustc_hash::FxHashMap;
+
+// After `prefix` steps the states repeat every `period` steps
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cycle {
+    pub prefix: usize,
+    pub period: usize,
+}
+
+impl Cycle {
+    // The first step with the same state as after n steps
+    pub fn reduce(&self, n: usize) -> usize {
+        if n < self.prefix {
+            n
+        } else {
+            self.prefix + (n - self.prefix) % self.period
+        }
+    }
+}
+
+// Remembers the step at which every key was first seen, only the keys are stored so they can be made a lot
+// smaller than the states
+pub fn find<S, K: Hash + Eq>(mut state: S, mut step: impl FnMut(&mut S), key: impl Fn(&S) -> K) -> Cycle {
+    let mut seen = FxHashMap::default();
+    for i in 0.. {
+        match seen.entry(key(&state)) {
+            Entry::Occupied(first) => return Cycle { prefix: *first.get(), period: i - first.get() },
+            Entry::Vacant(entry) => entry.insert(i),
+        };
+        step(&mut state);
+    }
+    unreachable!()
+}
+
+// Brent's algorithm, only keeps two states around but steps more often than hashing
+pub fn brent<S: Clone + Eq>(start: &S, mut step: impl FnMut(&mut S)) -> Cycle {
+    // Search for the period with a tortoise that teleports to the hare at every power of two
+    let mut power = 1;
+    let mut period = 1;
+    let mut tortoise = start.clone();
+    let mut hare = start.clone();
+    step(&mut hare);
+    while tortoise != hare {
+        if power == period {
+            tortoise = hare.clone();
+            power *= 2;
+            period = 0;
+        }
+        step(&mut hare);
+        period += 1;
+    }
+
+    // With the hare a period ahead, both meet at the start of the cycle
+    let mut tortoise = start.clone();
+    let mut hare = start.clone();
+    for _ in 0..period {
+        step(&mut hare);
+    }
+    let mut prefix = 0;
+    while tortoise != hare {
+        step(&mut tortoise);
+        step(&mut hare);
+        prefix += 1;
+    }
+
+    Cycle { prefix, period }
+}
+
+// The state after n steps, once a state repeats only the steps within one period are left to do
+pub fn fast_forward<S, K: Hash + Eq>(mut state: S, mut step: impl FnMut(&mut S), key: impl Fn(&S) -> K, n: usize) -> S {
+    let mut seen = FxHashMap::default();
+    let mut i = 0;
+    while i < n {
+        if let Some(first) = seen.insert(key(&state), i) {
+            let cycle = Cycle { prefix: first, period: i - first };
+            // The state at step i is the same as the state at step prefix
+            for _ in cycle.prefix..cycle.reduce(n) {
+                step(&mut state);
+            }
+            return state;
+        }
+        step(&mut state);
+        i += 1;
+    }
+    state
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 0, 1, 2, 3, 4, 5, 6, 3, 4, ...
+    fn step(x: &mut u32) {
+        *x = if *x < 6 { *x + 1 } else { 3 };
+    }
+
+    #[test]
+    fn small_cycle() {
+        let cycle = Cycle { prefix: 3, period: 4 };
+        assert_eq!(find(0, step, |&x| x), cycle);
+        assert_eq!(brent(&0, step), cycle);
+        assert_eq!(cycle.reduce(2), 2);
+        assert_eq!(cycle.reduce(7), 3);
+        assert_eq!(cycle.reduce(1_000_000_000), 4);
+
+        for n in 0..30 {
+            let mut naive = 0;
+            for _ in 0..n {
+                step(&mut naive);
+            }
+            assert_eq!(fast_forward(0, step, |&x| x, n), naive);
+        }
+        assert_eq!(fast_forward(0, step, |&x| x, 1_000_000_006), 6);
+    }
+
+    #[test]
+    fn pure_cycles() {
+        // Starting on the cycle there is no prefix, a fixed point has period 1
+        assert_eq!(find(4, step, |&x| x), Cycle { prefix: 0, period: 4 });
+        assert_eq!(brent(&4, step), Cycle { prefix: 0, period: 4 });
+        assert_eq!(brent(&7, |x| *x = 7), Cycle { prefix: 0, period: 1 });
+    }
+
+    #[test]
+    fn hashing_and_brent_agree() {
+        let quadratic = |x: &mut u64| *x = (*x * *x + 1) % 1009;
+        for start in [0, 2, 17, 500, 1008] {
+            assert_eq!(find(start, quadratic, |&x| x), brent(&start, quadratic));
+        }
+    }
+}
@@ -8,106 +8,140 @@ struct MappingEntry {
     source: Range<usize>
 }
 
-struct Almanac {
-    seeds: Vec<usize>,
-    mappings: Vec<Vec<MappingEntry>>
+// Piecewise offset function on 0..usize::MAX. Piece i covers pieces[i].0 up to the start of the next piece
+// and adds pieces[i].1 to every value in it. The first piece always starts at 0.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IntervalMap {
+    pieces: Vec<(usize, isize)>
 }
 
-#[inline]
-fn apply_mapping(seed: usize, entry: &MappingEntry) -> usize {
-    (seed - entry.source.start) + entry.destination.start
-}
+impl IntervalMap {
+    pub fn identity() -> Self {
+        IntervalMap { pieces: vec![(0, 0)] }
+    }
 
-#[inline]
-fn apply_mapping_rev(location: usize, entry: &MappingEntry) -> usize {
-    (location - entry.destination.start) + entry.source.start
-}
+    fn from_entries(entries: &[MappingEntry]) -> Self {
+        let mut pieces = vec![(0, 0)];
+        let mut entries: Vec<&MappingEntry> = entries.iter().collect();
+        entries.sort_by_key(|entry| entry.source.start);
 
-fn update_seed(mut seed: usize, almanac: &Almanac) -> usize {
-    // Mappings are in the correct order so we can just loop over the mappings and update the seed
-    for mapping in &almanac.mappings {
-        for entry in mapping {
-            if entry.source.contains(&seed) {
-                seed = apply_mapping(seed, entry);
-                break;
-            }
+        for entry in entries {
+            let offset = entry.destination.start as isize - entry.source.start as isize;
+            pieces.push((entry.source.start, offset));
+            pieces.push((entry.source.end, 0));
         }
+        IntervalMap::normalized(pieces)
     }
-    seed
-}
 
-fn update_location(mut location: usize, almanac: &Almanac) -> usize {
-    for mapping in almanac.mappings.iter().rev() {
-        for entry in mapping {
-            if entry.destination.contains(&location) {
-                location = apply_mapping_rev(location, entry);
-                break;
+    // Later pieces win when several start at the same value, adjacent pieces with the same offset are merged
+    fn normalized(mut pieces: Vec<(usize, isize)>) -> Self {
+        pieces.sort_by_key(|&(start, _)| start);
+        let mut result: Vec<(usize, isize)> = vec![];
+        for (start, offset) in pieces {
+            if let Some(last) = result.last_mut() {
+                if last.0 == start {
+                    *last = (start, offset);
+                    continue;
+                }
             }
+            result.push((start, offset));
         }
+        result.dedup_by(|next, previous| next.1 == previous.1);
+        IntervalMap { pieces: result }
+    }
+
+    fn end(&self, piece: usize) -> usize {
+        self.pieces.get(piece + 1).map_or(usize::MAX, |&(start, _)| start)
+    }
+
+    fn piece(&self, value: usize) -> usize {
+        self.pieces.partition_point(|&(start, _)| start <= value) - 1
+    }
+
+    pub fn apply(&self, value: usize) -> usize {
+        value.wrapping_add_signed(self.pieces[self.piece(value)].1)
     }
-    location
-}
 
-fn update_range(mut ranges: Vec<Range<usize>>, almanac: &Almanac) -> Vec<Range<usize>> {
-    // Mappings are in the correct order so we can just loop over the mappings and update the ranges
-    for mapping in &almanac.mappings {
-        let mut new_ranges = vec![];
-        for cur_range in ranges {
-            let mut range = cur_range.clone();
-            // Mapping entries are sorted on start of source and tiebreaker on end of source
-            // So we can just loop and update the ranges
-            for entry in mapping {
-                // Check if we still need to map the range further
-                if range.start < range.end && range.end >= entry.source.start {
-                    new_ranges.extend(range.consume_and_map(entry));
-                } else {
+    // The map that applies `self` first and `next` afterwards
+    pub fn compose(&self, next: &IntervalMap) -> IntervalMap {
+        let mut pieces = vec![];
+        for (idx, &(start, offset)) in self.pieces.iter().enumerate() {
+            let end = self.end(idx);
+            let image_start = start.wrapping_add_signed(offset);
+            // Breakpoints of `next` inside the image split this piece further
+            let mut piece = next.piece(image_start);
+            let mut current = start;
+            while current < end {
+                pieces.push((current, offset + next.pieces[piece].1));
+                let image_end = next.end(piece);
+                if image_end == usize::MAX {
                     break;
                 }
-            }
-            // The case when there are no mappings for the range or for the tail of the range
-            if range.start < range.end {
-                new_ranges.push(range);
+                current = image_end.wrapping_add_signed(-offset);
+                piece += 1;
             }
         }
-        ranges = new_ranges;
+        IntervalMap::normalized(pieces)
     }
-    ranges
-}
-
-trait RangeMerge<Rhs=Self> {
-    type Output;
-    fn overlap(&self, rhs: &Rhs) -> bool;
 
-    fn consume_and_map(&mut self, mapping_entry: &MappingEntry) -> Vec<Self::Output>;
-}
-
-impl RangeMerge for Range<usize> {
-    type Output = Range<usize>;
-    #[inline]
-    fn overlap(&self, other: &Self) -> bool {
-        self.start <= other.end && self.end >= other.start
+    // Only bijections can be inverted, `None` when two pieces overlap or a value is never reached
+    pub fn invert(&self) -> Option<IntervalMap> {
+        let mut images: Vec<(usize, usize, isize)> = (0..self.pieces.len())
+            .map(|idx| {
+                let (start, offset) = self.pieces[idx];
+                let end = self.end(idx);
+                let image_end = if end == usize::MAX { usize::MAX } else { end.wrapping_add_signed(offset) };
+                (start.wrapping_add_signed(offset), image_end, -offset)
+            })
+            .collect();
+        images.sort_unstable();
+
+        let mut expected = 0;
+        for &(start, end, _) in &images {
+            if start != expected {
+                return None;
+            }
+            expected = end;
+        }
+        Some(IntervalMap::normalized(images.into_iter().map(|(start, _, offset)| (start, offset)).collect()))
     }
 
-    fn consume_and_map(&mut self, entry: &MappingEntry) -> Vec<Self::Output> {
+    pub fn apply_ranges(&self, ranges: &[Range<usize>]) -> Vec<Range<usize>> {
         let mut result = vec![];
-        // Check if we can use this entry to map (part of) the range
-        if self.overlap(&entry.source) {
-            if self.start < entry.source.start {
-                result.push(self.start..entry.source.start);
-                self.start = entry.source.start;
-            }
-            if self.end < entry.source.end {
-                result.push(apply_mapping(self.start, entry)..apply_mapping(self.end, entry));
-                self.start = self.end;
-            } else {
-                result.push(apply_mapping(self.start, entry)..apply_mapping(entry.source.end, entry));
-                self.start = entry.source.end;
+        for range in ranges {
+            let mut piece = self.piece(range.start);
+            let mut current = range.start;
+            while current < range.end {
+                let end = self.end(piece).min(range.end);
+                let offset = self.pieces[piece].1;
+                result.push(current.wrapping_add_signed(offset)..end.wrapping_add_signed(offset));
+                current = end;
+                piece += 1;
             }
         }
         result
     }
+
+    // Smallest value of the map over the ranges, every piece is increasing so only its first value matters
+    pub fn min(&self, ranges: &[Range<usize>]) -> Option<usize> {
+        self.apply_ranges(ranges).iter()
+            .filter(|range| range.start < range.end)
+            .map(|range| range.start)
+            .min()
+    }
+}
+
+struct Almanac {
+    seeds: Vec<usize>,
+    mappings: Vec<IntervalMap>
 }
 
+impl Almanac {
+    // All mappings composed into one seed to location map
+    fn location(&self) -> IntervalMap {
+        self.mappings.iter().fold(IntervalMap::identity(), |map, next| map.compose(next))
+    }
+}
 
 #[aoc_generator(day5)]
 fn parse(input: &str) -> Almanac {
@@ -130,9 +164,7 @@ fn parse(input: &str) -> Almanac {
             };
             mapping.push(mapping_entry);
         }
-        // Needs this sort for part 2
-        mapping.sort_by_key(|k| (k.source.start, k.source.end));
-        mappings.push(mapping);
+        mappings.push(IntervalMap::from_entries(&mapping));
     }
 
     Almanac {
@@ -141,52 +173,56 @@ fn parse(input: &str) -> Almanac {
     }
 }
 
+fn seed_ranges(almanac: &Almanac) -> Vec<Range<usize>> {
+    almanac.seeds
+        .chunks(2)
+        .map(|range| range[0]..range[0]+range[1])
+        .collect()
+}
+
 #[aoc(day5, part1)]
 fn part1(input: &Almanac) -> usize {
+    let location = input.location();
     input.seeds
         .iter()
-        .map(|&seed| update_seed(seed, input))
+        .map(|&seed| location.apply(seed))
         .min()
         .unwrap()
 }
 
 #[aoc(day5, part2)]
 fn part2(input: &Almanac) -> usize {
-    let ranges: Vec<Range<usize>> = input.seeds
-        .chunks(2)
-        .map(|range| range[0]..range[0]+range[1])
-        .collect();
-
-    let result = update_range(ranges, input);
-    result.iter().min_by_key(|k| k.start).unwrap().start
+    input.location().min(&seed_ranges(input)).unwrap()
 }
 
 #[aoc(day5, part2, bruteforce)]
 fn part2_bruteforce(input: &Almanac) -> usize {
-    let ranges: Vec<Range<usize>> = input.seeds
-        .chunks(2)
-        .map(|range| range[0]..range[0]+range[1])
-        .collect();
-
-    ranges.par_iter()
+    let location = input.location();
+    seed_ranges(input).par_iter()
         .map(|range| range.clone()
-            .map(|seed| update_seed(seed, input))
+            .map(|seed| location.apply(seed))
             .min()
             .unwrap()
         ).min()
         .unwrap()
 }
 
-#[aoc(day5, part2, backward_bruteforce)]
-fn part2_backward_bruteforce(input: &Almanac) -> usize {
-    let ranges: Vec<Range<usize>> = input.seeds
-        .chunks(2)
-        .map(|range| range[0]..range[0]+range[1])
-        .collect();
-    for location in 1..usize::MAX {
-        let seed = update_location(location, input);
-
-        if ranges.iter().any(|range| range.contains(&seed)) {
+#[aoc(day5, part2, backward)]
+fn part2_backward(input: &Almanac) -> usize {
+    // Walk the locations in increasing order, piece by piece, until one comes from a seed
+    let seeds = input.location().invert().unwrap();
+    let ranges = seed_ranges(input);
+    for idx in 0..seeds.pieces.len() {
+        let (start, offset) = seeds.pieces[idx];
+        let locations = start..seeds.end(idx);
+        let first = ranges.iter()
+            .filter_map(|range| {
+                let seed_start = range.start.max(locations.start.wrapping_add_signed(offset));
+                let seed_end = range.end.min(locations.end.saturating_add_signed(offset));
+                (seed_start < seed_end).then_some(seed_start.wrapping_add_signed(-offset))
+            })
+            .min();
+        if let Some(location) = first {
             return location;
         }
     }
@@ -242,4 +278,34 @@ humidity-to-location map:
     fn part2_example() {
         assert_eq!(part2(&parse(EXAMPLE)), 46);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn part2_variants() {
+        let almanac = parse(EXAMPLE);
+        assert_eq!(part2_bruteforce(&almanac), 46);
+        assert_eq!(part2_backward(&almanac), 46);
+    }
+
+    #[test]
+    fn composed_map() {
+        let almanac = parse(EXAMPLE);
+        let location = almanac.location();
+        let inverse = location.invert().unwrap();
+        for seed in 0..200 {
+            let layered = almanac.mappings.iter().fold(seed, |value, map| map.apply(value));
+            assert_eq!(location.apply(seed), layered);
+            assert_eq!(inverse.apply(layered), seed);
+        }
+        assert_eq!(location.apply(79), 82);
+        assert_eq!(location.compose(&inverse), IntervalMap::identity());
+    }
+
+    #[test]
+    fn ranges() {
+        let map = IntervalMap::normalized(vec![(0, 0), (10, 5), (20, -20), (30, 0)]);
+        assert_eq!(map.apply_ranges(&[5..25, 40..41]), vec![5..10, 15..25, 0..5, 40..41]);
+        assert_eq!(map.min(&[5..25, 40..41]), Some(0));
+        assert_eq!(map.min(&[12..18, 45..50]), Some(17));
+        assert_eq!(map.invert(), None);
+    }
+}
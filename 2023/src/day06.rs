@@ -1,7 +1,10 @@
 use std::iter::zip;
 use aoc_runner_derive::{aoc, aoc_generator};
+use num::integer::Roots;
+use num::{BigUint, Integer};
+
 #[aoc_generator(day6, part1)]
-fn parse1(input: &str) -> (Vec<u32>, Vec<u32>) {
+fn parse1(input: &str) -> (Vec<u128>, Vec<u128>) {
     let (time, distance) = input.split_once('\n').unwrap();
     let time = time.split_whitespace().skip(1).map(|n| n.parse().unwrap()).collect();
     let distance = distance.split_whitespace().skip(1).map(|n| n.parse().unwrap()).collect();
@@ -9,15 +12,58 @@ fn parse1(input: &str) -> (Vec<u32>, Vec<u32>) {
 }
 
 #[aoc_generator(day6, part2)]
-fn parse2(input: &str) -> (u64, u64) {
+fn parse2(input: &str) -> (BigUint, BigUint) {
     let (time, distance) = input.split_once('\n').unwrap();
     let time = time.split(':').nth(1).unwrap().replace(' ', "");
     let distance = distance.split(':').nth(1).unwrap().replace(' ', "");
-    (time.parse().unwrap(), distance.parse().unwrap())
+    (time.trim().parse().unwrap(), distance.trim().parse().unwrap())
+}
+
+// Amount of hold times h with h * (time - h) > distance.
+// The roots of h^2 - time * h + distance are (time -+ sqrt(time^2 - 4 * distance)) / 2, the integer square root
+// gets the lower boundary within one of the real one and the boundary is corrected exactly from there.
+// Winning hold times are symmetric around time / 2, so the upper boundary is time - lower.
+fn ways<T: Integer + Roots + Clone + From<u8>>(time: &T, distance: &T) -> T {
+    let four = T::from(4);
+    let square = time.clone() * time.clone();
+    let four_distance = four * distance.clone();
+    if square <= four_distance {
+        return T::zero();
+    }
+    let root = (square - four_distance).sqrt();
+
+    let wins = |hold: &T| hold.clone() * (time.clone() - hold.clone()) > *distance;
+    let mut lower = (time.clone() - root) / T::from(2);
+    while lower > T::zero() && wins(&(lower.clone() - T::one())) {
+        lower = lower - T::one();
+    }
+    while !wins(&lower) {
+        lower = lower + T::one();
+        if lower.clone() * T::from(2) > *time {
+            return T::zero();
+        }
+    }
+    time.clone() - lower.clone() - lower + T::one()
+}
+
+// Stays in u128 while time^2 fits and only falls back to big integers for huge races
+fn ways_u128(time: u128, distance: u128) -> u128 {
+    match (time.checked_mul(time), distance.checked_mul(4)) {
+        (Some(_), Some(_)) => ways(&time, &distance),
+        _ => ways(&BigUint::from(time), &BigUint::from(distance)).try_into().unwrap(),
+    }
 }
 
 #[aoc(day6, part1)]
-fn part1(input: &(Vec<u32>, Vec<u32>)) -> u32 {
+fn part1(input: &(Vec<u128>, Vec<u128>)) -> u128 {
+    let (times, distances) = input;
+    zip(times, distances)
+        .map(|(&time, &distance)| ways_u128(time, distance))
+        .product()
+}
+
+#[aoc(day6, part1, bruteforce)]
+fn part1_bruteforce(input: &(Vec<u128>, Vec<u128>)) -> u128 {
     let (times, distances) = input;
     let mut result = 1;
     for (time, distance) in zip(times, distances) {
@@ -32,41 +78,12 @@ fn part1(input: &(Vec<u32>, Vec<u32>)) -> u32 {
     result
 }
 
-#[aoc(day6, part1, quadractic)]
-fn part1_q(input: &(Vec<u32>, Vec<u32>)) -> u32 {
-    let (times, distances) = input;
-    let mut result = 1;
-    for (&time, &distance) in zip(times, distances) {
-        let min = ((time as f32) - ((time.pow(2) - 4 * distance) as f32).sqrt()) / 2.0;
-        let max = ((time as f32) + ((time.pow(2) - 4 * distance) as f32).sqrt()) / 2.0;
-
-        result *= (max.ceil() - min.floor() - 1.0) as u32
-    }
-    result
-}
-
 #[aoc(day6, part2)]
-fn part2(input: &(u64, u64)) -> u64 {
+fn part2(input: &(BigUint, BigUint)) -> BigUint {
     let (time, distance) = input;
-    let mut count = 0;
-    for i in 0..=*time {
-        if i * (time - i) > *distance {
-            count += 1
-        }
-    }
-    count
+    ways(time, distance)
 }
 
-#[aoc(day6, part2, quadractic)]
-fn part2_q(input: &(u64, u64)) -> u64 {
-    let (time, distance) = *input;
-    let min = ((time as f64) - ((time.pow(2) - 4 * distance) as f64).sqrt()) / 2.0;
-    let max = ((time as f64) + ((time.pow(2) - 4 * distance) as f64).sqrt()) / 2.0;
-
-    (max.ceil() - min.floor() - 1.0) as u64
-}
-
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -77,10 +94,42 @@ Distance:  9  40  200";
     #[test]
     fn part1_example() {
         assert_eq!(part1(&parse1(EXAMPLE)), 288);
+        assert_eq!(part1_bruteforce(&parse1(EXAMPLE)), 288);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse2(EXAMPLE)), 71503);
+        assert_eq!(part2(&parse2(EXAMPLE)), BigUint::from(71503u32));
+    }
+
+    #[test]
+    fn perfect_squares() {
+        // 10^2 - 4 * 21 = 16, holding exactly 3 or 7 only ties the record
+        assert_eq!(ways_u128(10, 21), 3);
+        assert_eq!(ways_u128(10, 24), 1);
+        assert_eq!(ways_u128(10, 25), 0);
+        assert_eq!(ways_u128(10, 100), 0);
+        assert_eq!(ways_u128(0, 0), 0);
+        for time in 0..60u128 {
+            for distance in 0..(time * time / 4 + 2) {
+                let expected = (0..=time).filter(|hold| hold * (time - hold) > distance).count() as u128;
+                assert_eq!(ways_u128(time, distance), expected, "time {time}, distance {distance}");
+            }
+        }
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn huge_races() {
+        // Holding a or b with a + b = time exactly ties the record a * b, time^2 no longer fits in u128
+        let (a, b) = ((1u128 << 63) - 12, (1u128 << 63) + 1000);
+        assert_eq!(ways_u128(a + b, a * b), b - a - 1);
+        assert_eq!(ways_u128(a + b, a * b - 1), b - a + 1);
+
+        // Only holding exactly half of the time beats a record of (time / 2)^2 - 1
+        let time = BigUint::from(10u32).pow(39);
+        let half = &time / 2u32;
+        let distance = &half * &half - 1u32;
+        assert_eq!(ways(&time, &distance), BigUint::from(1u32));
+        assert_eq!(ways(&time, &(distance + 1u32)), BigUint::from(0u32));
+    }
+}
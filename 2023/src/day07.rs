@@ -1,152 +1,226 @@
+use std::cmp::Reverse;
 use aoc_runner_derive::{aoc, aoc_generator};
 use itertools::Itertools;
-use rustc_hash::FxHashMap;
-
-#[inline]
-fn get_card_value(card: char, part2: bool) -> u8 {
-    if !part2 {
-        match card {
-            'A' => 12,
-            'K' => 11,
-            'Q' => 10,
-            'J' => 9,
-            'T' => 8,
-            num => num.to_digit(10).unwrap() as u8 - 2,
-        }
-    } else {
-        match card {
-            'A' => 12,
-            'K' => 11,
-            'Q' => 10,
-            'J' => 0,
-            'T' => 9,
-            num => num.to_digit(10).unwrap() as u8 - 1,
-        }
-    }
+
+const SUITS: &str = "shdc";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Card {
+    pub rank: char,
+    pub suit: Option<char>,
 }
 
-struct Hand {
-    bid: u32,
-    // First value is the hand_type, the following values are the cards in order
-    value: [u8; 6]
+// A hand is parsed without any rules, so the same input can be scored under different rule sets
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hand {
+    pub cards: Vec<Card>,
+    pub bid: u32,
 }
 
 impl Hand {
-    fn new(line: &str, part2: bool) -> Self {
+    // Cards are ranks optionally followed by a lowercase suit, "32T3K" or "AhKhQhJhTh"
+    fn new(line: &str) -> Self {
         let (chars, bid) = line.split_once(' ').unwrap();
 
-        let bid = bid.parse().unwrap();
-
-        let mut values = [0; 6];
-        let mut chars = chars.chars();
-        let mut jokers = 0;
-        for value in values.iter_mut().skip(1) {
-            let card = chars.next().unwrap();
-            let card_value = get_card_value(card, part2);
-            if part2 && card_value == 0 {
-                jokers += 1;
+        let mut cards: Vec<Card> = vec![];
+        for ch in chars.chars() {
+            match cards.last_mut() {
+                Some(card) if SUITS.contains(ch) && card.suit.is_none() => card.suit = Some(ch),
+                _ => cards.push(Card { rank: ch, suit: None }),
             }
-            *value = card_value;
         }
-        values[0] = Hand::hand_type(&values, jokers).into();
 
         Hand {
-            bid,
-            value: values
+            cards,
+            bid: bid.trim().parse().unwrap(),
         }
     }
+}
 
-    fn hand_type(value: &[u8; 6], jokers: u8) -> HandType {
-        let mut cards = [0;13];
-        for card in value.iter().skip(1) {
-            cards[*card as usize] += 1
-        }
-        if jokers != 0 {
-            // Remove jokers, they will be added to the card with highest count
-            cards[0] = 0;
-        }
-        cards.sort_unstable();
-        cards.reverse();
-        // Add jokers to the card with the highest count
-        match cards[0] + jokers {
-            5 => HandType::Five,
-            4 => HandType::Four,
-            3 if cards[1] == 2 => HandType::Full,
-            3 => HandType::Three,
-            2 if cards[1] == 2 => HandType::Pair,
-            2 => HandType::Two,
-            1 => HandType::High,
-            _ => unreachable!()
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Category {
+    HighCard,
+    OnePair,
+    TwoPair,
+    ThreeOfAKind,
+    Straight,
+    Flush,
+    FullHouse,
+    FourOfAKind,
+    StraightFlush,
+    FiveOfAKind,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Scheme {
+    // Only counts matter, ties are broken by the cards in the order they were dealt
+    CamelCards,
+    // Straights and flushes count too, ties are broken by the ranks of the groups
+    Poker,
+}
+
+// The category is compared first and the key breaks ties
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Evaluation {
+    pub category: Category,
+    pub key: Vec<u8>,
+}
+
+pub struct Rules {
+    // Ranks from lowest to highest
+    order: Vec<char>,
+    // Ranks that stand in for whatever card makes the hand best
+    wildcards: Vec<char>,
+    scheme: Scheme,
+}
+
+impl Rules {
+    pub fn new(order: &str, wildcards: &str, scheme: Scheme) -> Self {
+        Rules {
+            order: order.chars().collect(),
+            wildcards: wildcards.chars().collect(),
+            scheme,
         }
     }
 
-    #[allow(dead_code)]
-    // Some experiments I did to see if the array sort is faster than a fast hashmap
-    fn hand_type_hashmap(value: &[u8; 6], jokers: u8) -> HandType {
-        let mut cards: FxHashMap<u8, u8> = FxHashMap::default();
-        for card in value.iter().skip(1) {
-            let count = cards.entry(*card).or_insert(0);
-            *count += 1;
+    pub fn camel_cards() -> Self {
+        Rules::new("23456789TJQKA", "", Scheme::CamelCards)
+    }
+
+    pub fn camel_cards_jokers() -> Self {
+        Rules::new("J23456789TQKA", "J", Scheme::CamelCards)
+    }
+
+    pub fn poker() -> Self {
+        Rules::new("23456789TJQKA", "", Scheme::Poker)
+    }
+
+    fn value(&self, rank: char) -> Option<u8> {
+        self.order.iter().position(|&r| r == rank).map(|value| value as u8)
+    }
+
+    // None when the hand contains a rank these rules don't know
+    pub fn evaluate(&self, hand: &Hand) -> Option<Evaluation> {
+        let values: Vec<u8> = hand.cards.iter().map(|card| self.value(card.rank)).collect::<Option<_>>()?;
+        let (wild, natural): (Vec<&Card>, Vec<&Card>) = hand.cards.iter()
+            .partition(|card| self.wildcards.contains(&card.rank));
+        let jokers = wild.len();
+
+        // Groups of equal ranks, largest and highest first
+        let mut groups: Vec<(usize, u8)> = natural.iter()
+            .map(|card| self.value(card.rank).unwrap())
+            .counts()
+            .into_iter()
+            .map(|(value, count)| (count, value))
+            .collect();
+        groups.sort_unstable_by_key(|&group| Reverse(group));
+        // Wildcards always join the largest group
+        match groups.first_mut() {
+            Some(group) => group.0 += jokers,
+            None if jokers > 0 => groups.push((jokers, self.order.len() as u8 - 1)),
+            None => {}
         }
-        if jokers != 0 {
-            cards.remove(&0);
+
+        let largest = groups.first().map_or(0, |group| group.0);
+        let second = groups.get(1).map_or(0, |group| group.0);
+        let mut category = match (largest, second) {
+            (5.., _) => Category::FiveOfAKind,
+            (4, _) => Category::FourOfAKind,
+            (3, 2) => Category::FullHouse,
+            (3, _) => Category::ThreeOfAKind,
+            (2, 2) => Category::TwoPair,
+            (2, _) => Category::OnePair,
+            _ => Category::HighCard,
+        };
+
+        let key = match self.scheme {
+            Scheme::CamelCards => values,
+            Scheme::Poker => {
+                let straight = self.straight(&natural, hand.cards.len());
+                let flush = Self::flush(&natural, &wild);
+                let better = match (straight, flush) {
+                    (Some(_), true) => Some(Category::StraightFlush),
+                    (_, true) => Some(Category::Flush),
+                    (Some(_), false) => Some(Category::Straight),
+                    (None, false) => None,
+                };
+                match (better, straight) {
+                    (Some(better), Some(high)) if better > category => {
+                        category = better;
+                        vec![high]
+                    }
+                    (Some(better), _) if better > category => {
+                        // A flush is ranked by its cards, wildcards become the highest rank
+                        category = better;
+                        let top = self.order.len() as u8 - 1;
+                        let mut key: Vec<u8> = natural.iter().map(|card| self.value(card.rank).unwrap()).collect();
+                        key.extend(std::iter::repeat_n(top, jokers));
+                        key.sort_unstable_by_key(|&value| Reverse(value));
+                        key
+                    }
+                    _ => groups.iter().flat_map(|&(count, value)| std::iter::repeat_n(value, count)).collect(),
+                }
+            }
+        };
+
+        Some(Evaluation { category, key })
+    }
+
+    // Highest card of the best straight the cards can make, the highest rank may also play below the lowest
+    fn straight(&self, natural: &[&Card], length: usize) -> Option<u8> {
+        if length < 2 || length > self.order.len() {
+            return None;
         }
-        let (_, max_count) = cards.iter().max_by_key(|entry | entry.1).unwrap_or((&0, &0));
-        match max_count + jokers {
-            5 => HandType::Five,
-            4 => HandType::Four,
-            3 if cards.len() == 2 => HandType::Full,
-            3 => HandType::Three,
-            2 if cards.len() == 3 => HandType::Pair,
-            2 => HandType::Two,
-            1 => HandType::High,
-            _ => unreachable!()
+        let top = self.order.len() as i32 - 1;
+        let values: Vec<i32> = natural.iter().map(|card| self.value(card.rank).unwrap() as i32).collect();
+        if !values.iter().all_unique() {
+            return None;
         }
-    }
-}
 
-enum HandType {
-    High,
-    Two,
-    Pair,
-    Three,
-    Full,
-    Four,
-    Five,
-}
+        [false, true].into_iter()
+            .filter_map(|low| {
+                let values: Vec<i32> = values.iter().map(|&v| if low && v == top { -1 } else { v }).collect();
+                let min = values.iter().min().copied().unwrap_or(top);
+                let max = values.iter().max().copied().unwrap_or(top);
+                // Wildcards fill the gaps first and then extend the straight upwards
+                let high = (min + length as i32 - 1).min(top);
+                (max - min < length as i32 && high - (length as i32 - 1) >= -1).then_some(high as u8)
+            })
+            .max()
+    }
 
-impl From<HandType> for u8 {
-    fn from(value: HandType) -> Self {
-        value as u8
+    fn flush(natural: &[&Card], wild: &[&Card]) -> bool {
+        if natural.iter().chain(wild).any(|card| card.suit.is_none()) {
+            return false;
+        }
+        natural.iter().map(|card| card.suit).all_equal()
     }
-}
 
-#[aoc_generator(day7, part1)]
-fn parse1(input: &str) -> Vec<Hand> {
-    input.lines().map(|line| Hand::new(line, false)).collect()
+    // Every hand wins its bid times its rank
+    pub fn winnings(&self, hands: &[Hand]) -> Option<u32> {
+        let evaluations: Vec<Evaluation> = hands.iter().map(|hand| self.evaluate(hand)).collect::<Option<_>>()?;
+        let total = hands.iter()
+            .zip(evaluations)
+            .sorted_unstable_by(|a, b| a.1.cmp(&b.1))
+            .enumerate()
+            .fold(0, |acc, (i, (hand, _))| acc + ((i as u32 + 1) * hand.bid));
+        Some(total)
+    }
 }
 
-#[aoc_generator(day7, part2)]
-fn parse2(input: &str) -> Vec<Hand> {
-    input.lines().map(|line| Hand::new(line, true)).collect()
+#[aoc_generator(day7)]
+fn parse(input: &str) -> Vec<Hand> {
+    input.lines().map(Hand::new).collect()
 }
 
 #[aoc(day7, part1)]
-fn part1(input: &[Hand]) -> u32 {
-    let hands = input.iter().sorted_unstable_by_key(|hand| hand.value);
-
-    hands
-        .enumerate()
-        .fold(0, |acc, (i, hand)| acc + ((i as u32 + 1) * hand.bid))
+fn part1(input: &[Hand]) -> Option<u32> {
+    Rules::camel_cards().winnings(input)
 }
 
 #[aoc(day7, part2)]
-fn part2(input: &[Hand]) -> u32 {
-    let hands = input.iter().sorted_unstable_by_key(|hand| hand.value);
-
-    hands
-        .enumerate()
-        .fold(0, |acc, (i, hand)| acc + ((i as u32 + 1) * hand.bid))
+fn part2(input: &[Hand]) -> Option<u32> {
+    Rules::camel_cards_jokers().winnings(input)
 }
 
 
@@ -162,11 +236,71 @@ QQQJA 483";
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse1(EXAMPLE)), 6440);
+        assert_eq!(part1(&parse(EXAMPLE)), Some(6440));
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse2(EXAMPLE)), 5905);
+        assert_eq!(part2(&parse(EXAMPLE)), Some(5905));
+    }
+
+    fn category(rules: &Rules, hand: &str) -> Category {
+        rules.evaluate(&Hand::new(&format!("{hand} 0"))).unwrap().category
+    }
+
+    #[test]
+    fn camel_categories() {
+        let (plain, jokers) = (Rules::camel_cards(), Rules::camel_cards_jokers());
+        assert_eq!(category(&plain, "T55J5"), Category::ThreeOfAKind);
+        assert_eq!(category(&jokers, "T55J5"), Category::FourOfAKind);
+        assert_eq!(category(&jokers, "JJJJJ"), Category::FiveOfAKind);
+        assert_eq!(category(&jokers, "23J45"), Category::OnePair);
+        assert_eq!(category(&plain, "23456"), Category::HighCard);
+        assert_eq!(category(&jokers, "2233J"), Category::FullHouse);
+        assert_eq!(plain.evaluate(&Hand::new("2345X 1")), None);
+    }
+
+    #[test]
+    fn poker_categories() {
+        let poker = Rules::poker();
+        assert_eq!(category(&poker, "AhKhQhJhTh"), Category::StraightFlush);
+        assert_eq!(category(&poker, "As2d3h4c5s"), Category::Straight);
+        assert_eq!(category(&poker, "2h7h9hJhKh"), Category::Flush);
+        assert_eq!(category(&poker, "2h2d2s7h7c"), Category::FullHouse);
+        assert_eq!(category(&poker, "KsAh2d3c4s"), Category::HighCard);
+        // Without suits there are no flushes
+        assert_eq!(category(&poker, "279JK"), Category::HighCard);
+        assert_eq!(category(&poker, "9TJQK"), Category::Straight);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn poker_ordering() {
+        let poker = Rules::poker();
+        let hands = "As2d3h4c5s 1
+2s3d4h5c6s 2
+2h7h9hJhKh 3
+AhAdAsKhKc 4
+2c3c4c5c6c 5
+ThJhQhKhAh 6
+AsAdKcKh2s 7
+AsAdKcKh3s 8";
+        let ranked: Vec<u32> = parse(hands).iter()
+            .sorted_by_key(|hand| poker.evaluate(hand).unwrap())
+            .map(|hand| hand.bid)
+            .collect();
+        assert_eq!(ranked, [7, 8, 1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn poker_wildcards() {
+        let wild = Rules::new("23456789TJQKA", "2", Scheme::Poker);
+        assert_eq!(category(&wild, "2hJhQhKhAh"), Category::StraightFlush);
+        assert_eq!(category(&wild, "2s9hThJh3d"), Category::OnePair);
+        assert_eq!(category(&wild, "2s9hThJhQd"), Category::Straight);
+        assert_eq!(category(&wild, "2s2hAhAdAs"), Category::FiveOfAKind);
+        assert_eq!(category(&wild, "2s4h7h9hKh"), Category::Flush);
+        // Wildcards extend the straight upwards as far as possible
+        let straight = wild.evaluate(&Hand::new("2s9hThJhQd 0")).unwrap();
+        assert_eq!(straight.key, [wild.value('K').unwrap()]);
+    }
+}
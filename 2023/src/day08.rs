@@ -1,8 +1,11 @@
 use aoc_runner_derive::aoc;
+use itertools::Itertools;
 use num::Integer;
 use rayon::prelude::*;
 use rustc_hash::FxHashMap;
 
+use crate::cycle;
+
 // #[aoc_generator(day8)]
 fn parse(input: &str) -> (Vec<char>, FxHashMap<&str, (&str, &str)>) {
     let (movement, connections) = input.split_once("\n\n").unwrap();
@@ -22,39 +25,128 @@ fn parse(input: &str) -> (Vec<char>, FxHashMap<&str, (&str, &str)>) {
     (movement, map)
 }
 
-fn solve<'a>(mut current: &'a str, movement: &Vec<char>, connections: &'a FxHashMap<&str, (&str, &str)>, part2: bool) -> usize {
-    let mut count = 0;
+// Every step at which a walk is on an end node: the hits before the cycle and the hits within one period of it,
+// after `start` steps the walk repeats every `period` steps
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Walk {
+    pub prefix_hits: Vec<u64>,
+    pub start: u64,
+    pub period: u64,
+    pub cycle_hits: Vec<u64>,
+}
 
-    while (part2 || current != "ZZZ") && (!part2 || !current.ends_with('Z')) {
-        let (left, right) = connections.get(current).unwrap();
-        if movement[count % movement.len()] == 'L' {
-            current = left;
+impl Walk {
+    fn hits(&self, step: u64) -> bool {
+        if step < self.start {
+            self.prefix_hits.contains(&step)
         } else {
-            current = right;
+            let offset = (step - self.start) % self.period;
+            self.cycle_hits.contains(&(self.start + offset))
         }
-        count += 1;
     }
-    count
+
+    fn first_hit(&self) -> Option<u64> {
+        self.prefix_hits.first().or(self.cycle_hits.first()).copied()
+    }
+}
+
+// The state of a walk is the node together with the position in the instructions, so it has to cycle
+fn walk<'a>(
+    current: &'a str,
+    movement: &[char],
+    connections: &'a FxHashMap<&str, (&str, &str)>,
+    is_end: impl Fn(&str) -> bool,
+) -> Walk {
+    let advance = |(node, index): &mut (&'a str, usize)| {
+        let (left, right) = connections.get(node).unwrap();
+        *node = if movement[*index] == 'L' { left } else { right };
+        *index = (*index + 1) % movement.len();
+    };
+    let cycle = cycle::find((current, 0), advance, |&state| state);
+
+    // Every hit is seen once walking up to the first repeated state
+    let mut state = (current, 0);
+    let mut hits = vec![];
+    for step in 0..(cycle.prefix + cycle.period) as u64 {
+        if is_end(state.0) {
+            hits.push(step);
+        }
+        advance(&mut state);
+    }
+
+    let start = cycle.prefix as u64;
+    let (prefix_hits, cycle_hits) = hits.into_iter().partition(|&hit| hit < start);
+    Walk {
+        prefix_hits,
+        start,
+        period: cycle.period as u64,
+        cycle_hits,
+    }
+}
+
+// Generalised Chinese Remainder Theorem, merges x = a (mod m) and x = b (mod n) even if m and n share factors
+fn crt((a, m): (i128, i128), (b, n): (i128, i128)) -> Option<(i128, i128)> {
+    let gcd = m.extended_gcd(&n);
+    if (b - a) % gcd.gcd != 0 {
+        return None;
+    }
+    let lcm = m / gcd.gcd * n;
+    let x = (a + (b - a) / gcd.gcd % (n / gcd.gcd) * gcd.x % (n / gcd.gcd) * m).rem_euclid(lcm);
+    Some((x, lcm))
+}
+
+// First step at which every walk is on an end node, None if that never happens
+pub fn first_common(walks: &[Walk]) -> Option<u64> {
+    if walks.is_empty() {
+        return None;
+    }
+
+    // The usual structure of the puzzle input: every walk only ends up on an end node at multiples of its period
+    if walks.iter().all(|walk| walk.prefix_hits.is_empty() && walk.cycle_hits == [walk.period]) {
+        return Some(walks.iter().fold(1, |acc, walk| acc.lcm(&walk.period)));
+    }
+
+    // Before every walk has entered its cycle, a common step is a prefix hit of the walk that enters last
+    let latest = walks.iter().max_by_key(|walk| walk.start)?;
+    if let Some(&step) = latest.prefix_hits.iter().find(|&&step| walks.iter().all(|walk| walk.hits(step))) {
+        return Some(step);
+    }
+
+    // Afterwards every walk is periodic, so combine each choice of hits into a single congruence
+    let after = latest.start as i128;
+    let mut congruences = vec![(0, 1)];
+    for walk in walks {
+        let period = walk.period as i128;
+        congruences = congruences.iter()
+            .cartesian_product(&walk.cycle_hits)
+            .filter_map(|(&congruence, &hit)| crt(congruence, ((hit as i128).rem_euclid(period), period)))
+            .unique()
+            .collect();
+    }
+
+    congruences.into_iter()
+        .map(|(x, modulus)| x + Integer::div_ceil(&(after - x).max(0), &modulus) * modulus)
+        .min()
+        .map(|step| step as u64)
 }
 
 #[aoc(day8, part1)]
-fn part1(input: &str) -> usize {
+fn part1(input: &str) -> Option<u64> {
     let (movement, connections) = &parse(input);
 
-    let current = "AAA";
-    solve(current, movement, connections, false)
+    walk("AAA", movement, connections, |node| node == "ZZZ").first_hit()
 }
 
 #[aoc(day8, part2)]
-fn part2(input: &str) -> usize {
+fn part2(input: &str) -> Option<u64> {
     let (movement, connections) = &parse(input);
 
-    let counts: Vec<usize> = connections.par_iter()
+    let walks: Vec<Walk> = connections.par_iter()
         .filter(|(key, _)| key.ends_with('A'))
-        .map(|(current, _)| solve(current, movement, connections, true))
+        .map(|(current, _)| walk(current, movement, connections, |node| node.ends_with('Z')))
         .collect();
 
-    counts.iter().skip(1).fold(*counts.first().unwrap(), |acc, num| acc.lcm(num))
+    first_common(&walks)
 }
 
 
@@ -91,16 +183,88 @@ XXX = (XXX, XXX)";
 
     #[test]
     fn part1_example1() {
-        assert_eq!(part1(EXAMPLE_1), 2);
+        assert_eq!(part1(EXAMPLE_1), Some(2));
     }
 
     #[test]
     fn part1_example2() {
-        assert_eq!(part1(EXAMPLE_2), 6);
+        assert_eq!(part1(EXAMPLE_2), Some(6));
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(EXAMPLE_PART2), 6);
+        assert_eq!(part2(EXAMPLE_PART2), Some(6));
     }
-}
\ No newline at end of file
+
+    // Every ghost takes the only path, 1Z is reached after 2, 5, 8, ... steps and 2Z after 3, 7, 11, ... steps
+    const OFFSET_CYCLES: &str = "L
+
+1A = (1B, 1B)
+1B = (1Z, 1Z)
+1Z = (1C, 1C)
+1C = (1D, 1D)
+1D = (1Z, 1Z)
+2A = (2B, 2B)
+2B = (2C, 2C)
+2C = (2Z, 2Z)
+2Z = (2D, 2D)
+2D = (2E, 2E)
+2E = (2F, 2F)
+2F = (2Z, 2Z)";
+
+    #[test]
+    fn walks() {
+        let (movement, connections) = &parse(OFFSET_CYCLES);
+        let walk = walk("1A", movement, connections, |node| node.ends_with('Z'));
+        assert_eq!(walk, Walk { prefix_hits: vec![], start: 2, period: 3, cycle_hits: vec![2] });
+        assert_eq!(walk.first_hit(), Some(2));
+    }
+
+    #[test]
+    fn offset_cycles() {
+        assert_eq!(part2(OFFSET_CYCLES), Some(11));
+    }
+
+    #[test]
+    fn never() {
+        // 1Z is reached at odd steps, 2Z at even steps
+        let input = "L
+
+1A = (1Z, 1Z)
+1Z = (1B, 1B)
+1B = (1Z, 1Z)
+2A = (2B, 2B)
+2B = (2Z, 2Z)
+2Z = (2C, 2C)
+2C = (2Z, 2Z)";
+        assert_eq!(part2(input), None);
+    }
+
+    #[test]
+    fn prefix_hits() {
+        // The first walk is only on an end node once, before it gets stuck in a cycle without any
+        let walks = [
+            Walk { prefix_hits: vec![1], start: 2, period: 1, cycle_hits: vec![] },
+            Walk { prefix_hits: vec![], start: 1, period: 2, cycle_hits: vec![1] },
+        ];
+        assert_eq!(first_common(&walks), Some(1));
+        let walks = [
+            Walk { prefix_hits: vec![1], start: 2, period: 1, cycle_hits: vec![] },
+            Walk { prefix_hits: vec![], start: 2, period: 2, cycle_hits: vec![2] },
+        ];
+        assert_eq!(first_common(&walks), None);
+        assert_eq!(first_common(&[]), None);
+    }
+
+    #[test]
+    fn shared_factors() {
+        // x = 3 (mod 6) and x = 7 (mod 10) meet at 27, x = 1 (mod 6) and x = 2 (mod 4) never do
+        assert_eq!(crt((3, 6), (7, 10)), Some((27, 30)));
+        assert_eq!(crt((1, 6), (2, 4)), None);
+        let walks = [
+            Walk { prefix_hits: vec![], start: 0, period: 6, cycle_hits: vec![3] },
+            Walk { prefix_hits: vec![], start: 5, period: 10, cycle_hits: vec![7, 10] },
+        ];
+        assert_eq!(first_common(&walks), Some(27));
+    }
+}
@@ -1,4 +1,7 @@
 use aoc_runner_derive::{aoc, aoc_generator};
+
+use crate::polynomial;
+
 #[aoc_generator(day9)]
 fn parse(input: &str) -> Vec<Vec<i32>> {
     input.lines().map(|line|
@@ -8,36 +11,23 @@ fn parse(input: &str) -> Vec<Vec<i32>> {
     ).collect()
 }
 
-fn solve(history: &[i32], part2: bool) -> i32 {
-    let mut history = history.to_vec();
-    if part2 {
-        history.reverse();
-    }
-
-    let mut result = 0;
-    while !history.iter().all(|&value| value == 0) {
-        result += history.last().unwrap();
-
-        let mut next_history = vec![];
-        for window in history.windows(2) {
-            next_history.push(window.last().unwrap() - window.first().unwrap());
-        }
-        history = next_history;
-    }
-    result
+// Fits the history and extrapolates to any index, -1 is the value before the history
+fn solve(history: &[i32], index: i64) -> i32 {
+    let fit = polynomial::fit(history);
+    fit.polynomial.value(index).try_into().unwrap()
 }
 
 #[aoc(day9, part1)]
 fn part1(input: &[Vec<i32>]) -> i32 {
     input.iter()
-        .map(|history| solve(history, false))
+        .map(|history| solve(history, history.len() as i64))
         .sum()
 }
 
 #[aoc(day9, part2)]
 fn part2(input: &[Vec<i32>]) -> i32 {
     input.iter()
-        .map(|history| solve(history, true))
+        .map(|history| solve(history, -1))
         .sum()
 }
 
@@ -59,4 +49,12 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&parse(EXAMPLE)), 2);
     }
+
+    #[test]
+    fn far_ahead() {
+        let history = &parse(EXAMPLE)[1];
+        assert_eq!(solve(history, 10), 66);
+        assert_eq!(solve(history, -3), 1);
+        assert_eq!(polynomial::fit(history).degree, 2);
+    }
 }
\ No newline at end of file
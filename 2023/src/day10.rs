@@ -1,273 +1,186 @@
-use std::ops::Add;
-
 use aoc_runner_derive::{aoc, aoc_generator};
-use num::abs;
 
-#[derive(PartialEq, Debug, Copy, Clone)]
-struct Pos {
-    y: u8,
-    x: u8
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+pub struct Pos {
+    pub y: usize,
+    pub x: usize
 }
 
 impl Pos {
-    fn new(y: u8, x: u8) -> Pos {
+    fn new(y: usize, x: usize) -> Pos {
         Pos { y, x }
     }
 }
 
-impl Add<(i8, i8)> for Pos {
-    type Output = Pos;
-
-    fn add(self, rhs: (i8, i8)) -> Self::Output {
-        Pos::new((self.y as i8 + rhs.0) as u8, (self.x as i8 + rhs.1) as u8)
-    }
+#[derive(PartialEq, Eq, Debug, Copy, Clone)]
+enum Direction {
+    North,
+    East,
+    South,
+    West,
 }
 
-#[derive(PartialEq, Copy, Clone)]
-struct Tile {
-    pos: Pos,
-    neighbours: Option<(Pos, Pos)>,
-    corner: bool,
-    char: char
-}
+impl Direction {
+    const ALL: [Direction; 4] = [Direction::North, Direction::East, Direction::South, Direction::West];
 
-impl Tile {
-    fn next(self, last: Pos) -> Pos {
-        let (neighbour1, neighbour2) = self.neighbours.unwrap();
-        if !(neighbour1 == last || neighbour2 == last) {
-            // n1 links to n2 but n2 doesn't link back to n1
-            panic!("{:?} links to {:?}, but {:?} doesn't link back to {:?}", last, self.pos, self.pos, last)
-        }
-        if neighbour1 == last {
-            neighbour2
-        } else {
-            neighbour1
+    fn opposite(self) -> Direction {
+        match self {
+            Direction::North => Direction::South,
+            Direction::East => Direction::West,
+            Direction::South => Direction::North,
+            Direction::West => Direction::East,
         }
     }
+}
 
-    fn connect_start(&mut self, map: &[Vec<Tile>]) {
-        // Start not connected yet
-        assert_eq!(self.neighbours, None, "Start is already connected");
-        let mut start_connections = Vec::with_capacity(4); // 4 Directions so max 4 items inserted
-        for dir in [(-1, 0), (0, 1), (1, 0), (0, -1)] {
-            let neighbour_pos = self.pos + dir;
-            if !bound_check(map, neighbour_pos) {
-                continue;
-            }
-            let neighbour = get_tile(map, neighbour_pos);
-            if neighbour.neighbours.is_none() {
-                // Neighbour is no pipe
-                continue;
-            }
-            let connections = neighbour.neighbours.unwrap();
-            // If links back to start
-            if connections.0 == self.pos || connections.1 == self.pos {
-                start_connections.push(neighbour_pos);
-            }
-        }
-        assert_eq!(start_connections.len(), 2, "Too much connections on start");
-        let neighbour1 = start_connections.first().unwrap();
-        let neighbour2 = start_connections.last().unwrap();
-        self.neighbours = Some((*neighbour1, *neighbour2));
-        self.corner = !(neighbour1.x == neighbour2.x || neighbour1.y == neighbour2.y);
+// The two directions a pipe connects, None for ground and the start
+fn connections(char: char) -> Option<[Direction; 2]> {
+    match char {
+        '|' => Some([Direction::North, Direction::South]),
+        '-' => Some([Direction::East, Direction::West]),
+        'L' => Some([Direction::North, Direction::East]),
+        'J' => Some([Direction::North, Direction::West]),
+        '7' => Some([Direction::South, Direction::West]),
+        'F' => Some([Direction::East, Direction::South]),
+        _ => None
     }
 }
 
-#[inline]
-fn get_tile(map: &[Vec<Tile>], pos: Pos) -> &Tile {
-    map.get(pos.y as usize - 1).unwrap().get(pos.x as usize - 1).unwrap()
+fn pipe(connection: [Direction; 2]) -> char {
+    ['|', '-', 'L', 'J', '7', 'F'].into_iter()
+        .find(|&char| {
+            let [a, b] = connections(char).unwrap();
+            connection == [a, b] || connection == [b, a]
+        })
+        .unwrap()
 }
 
-#[inline]
-fn bound_check(map: &[Vec<Tile>], pos: Pos) -> bool {
-    let y_range = 1..=map.len() as u8;
-    let x_range = 1..=map.first().unwrap().len() as u8;
+pub struct Maze {
+    tiles: Vec<Vec<char>>,
+    start: Pos,
+}
 
-    x_range.contains(&pos.x) && y_range.contains(&pos.y)
+pub struct Loop {
+    // Every tile of the loop in order, starting at the start
+    pub path: Vec<Pos>,
+    // The pipe hidden under the start
+    pub start_tile: char,
 }
 
+impl Maze {
+    fn get(&self, pos: Pos) -> char {
+        self.tiles[pos.y].get(pos.x).copied().unwrap_or('.')
+    }
+
+    fn step(&self, pos: Pos, direction: Direction) -> Option<Pos> {
+        let pos = match direction {
+            Direction::North => Pos::new(pos.y.checked_sub(1)?, pos.x),
+            Direction::East => Pos::new(pos.y, pos.x + 1),
+            Direction::South => Pos::new(pos.y + 1, pos.x),
+            Direction::West => Pos::new(pos.y, pos.x.checked_sub(1)?),
+        };
+        (pos.y < self.tiles.len() && pos.x < self.tiles[pos.y].len()).then_some(pos)
+    }
 
-#[aoc_generator(day10)]
-fn parse(input: &str) -> (Vec<Vec<Tile>>, Pos) {
-    let mut start_pos = None;
-    let mut result = vec![];
-    for (y, line) in input.lines().enumerate() {
-        // Make y bigger so we can check neighbours and don't have to worry for underflow on usize
-        let y_increased = y as u8 + 1;
-        let mut grid_line = vec![];
-        for (x, char) in line.chars().enumerate() {
-            // Make y bigger so we can check neighbours and don't have to worry for underflow on usize
-            let x_increased = x as u8 + 1;
-            let pos = Pos::new(y_increased, x_increased);
-            let north = Pos::new(y_increased - 1, x_increased);
-            let east = Pos::new(y_increased, x_increased + 1);
-            let south = Pos::new(y_increased + 1, x_increased);
-            let west = Pos::new(y_increased, x_increased - 1);
-            let tile = match char {
-                '|' => Tile{pos, neighbours: Some((north, south)), corner: false, char},
-                '-' => Tile{pos, neighbours: Some((east, west)), corner: false, char},
-                'L' => Tile{pos, neighbours: Some((north, east)), corner: true, char},
-                'J' => Tile{pos, neighbours: Some((north, west)), corner: true, char},
-                '7' => Tile{pos, neighbours: Some((south, west)), corner: true, char},
-                'F' => Tile{pos, neighbours: Some((south, east)), corner: true, char},
-                '.' => Tile{pos, neighbours: None, corner: false, char},
-                'S' => Tile{pos, neighbours: None, corner: false, char},
-                _ => unreachable!()
-            };
-            if char == 'S' {
-                start_pos = Some(pos);
+    // Follows the pipes leaving the start towards `direction`, pipes that don't link back end the walk.
+    // Returns the path and the direction in which the walk arrived back at the start.
+    fn follow(&self, mut direction: Direction) -> Option<(Vec<Pos>, Direction)> {
+        let mut path = vec![self.start];
+        let mut pos = self.start;
+        loop {
+            pos = self.step(pos, direction)?;
+            if pos == self.start {
+                return Some((path, direction));
             }
-            grid_line.push(tile);
+            let [a, b] = connections(self.get(pos))?;
+            direction = match direction.opposite() {
+                from if from == a => b,
+                from if from == b => a,
+                _ => return None,
+            };
+            path.push(pos);
         }
-        result.push(grid_line);
     }
-    if start_pos.is_none() {
-        unreachable!()
+
+    // Any pipe next to the start can link to it, the loop is the pair of links that closes
+    pub fn find_loop(&self) -> Option<Loop> {
+        Direction::ALL.into_iter().find_map(|direction| {
+            let (path, back) = self.follow(direction)?;
+            // A walk can't return along the direction it left, that would need the start to be a dead end
+            (back.opposite() != direction).then(|| Loop {
+                path,
+                start_tile: pipe([direction, back.opposite()]),
+            })
+        })
     }
-    (result, start_pos.unwrap())
+
+    // Tiles inside the loop, a tile is inside when the row left of it crosses the loop an odd amount of times.
+    // Only pipes going north are counted so a corner pair like F--J counts once and F--7 not at all.
+    pub fn enclosed(&self, maze_loop: &Loop) -> Vec<Vec<bool>> {
+        let mut on_loop: Vec<Vec<bool>> = self.tiles.iter().map(|row| vec![false; row.len()]).collect();
+        for pos in &maze_loop.path {
+            on_loop[pos.y][pos.x] = true;
+        }
+
+        let mut inside_mask = vec![];
+        for (y, row) in self.tiles.iter().enumerate() {
+            let mut inside = false;
+            let mut mask_row = vec![false; row.len()];
+            for (x, &char) in row.iter().enumerate() {
+                if on_loop[y][x] {
+                    let char = if Pos::new(y, x) == self.start { maze_loop.start_tile } else { char };
+                    if connections(char).unwrap().contains(&Direction::North) {
+                        inside = !inside;
+                    }
+                } else {
+                    mask_row[x] = inside;
+                }
+            }
+            inside_mask.push(mask_row);
+        }
+        inside_mask
+    }
+}
+
+
+#[aoc_generator(day10)]
+fn parse(input: &str) -> Maze {
+    let tiles: Vec<Vec<char>> = input.lines().map(|line| line.chars().collect()).collect();
+    let start = tiles.iter().enumerate()
+        .find_map(|(y, row)| row.iter().position(|&char| char == 'S').map(|x| Pos::new(y, x)))
+        .expect("No start in the maze");
+    Maze { tiles, start }
 }
 
 #[aoc(day10, part1)]
-fn part1(input: &(Vec<Vec<Tile>>, Pos)) -> u32 {
-    let (map_input, start) = input;
-    let map = &mut map_input.clone();
-    let start_tile = map.get_mut(start.y as usize - 1).unwrap().get_mut(start.x as usize - 1).unwrap();
-    start_tile.connect_start(map_input);
-
-    let start_tile = get_tile(map, *start);
-
-    let mut lasts = (*start, *start);
-    let mut nexts = start_tile.neighbours.unwrap();
-    let mut result = 1;
-    // Check if we close the loop
-    while nexts.0 != nexts.1 {
-        result += 1;
-        // find next
-        let pos = nexts;
-        let tile0 = get_tile(map, pos.0);
-        let tile1 = get_tile(map, pos.1);
-        let new_nexts = (tile0.next(lasts.0), tile1.next(lasts.1));
-        lasts = nexts;
-        nexts = new_nexts;
-    }
-    result
+fn part1(maze: &Maze) -> Option<usize> {
+    let maze_loop = maze.find_loop()?;
+    Some(maze_loop.path.len() / 2)
 }
 
 #[aoc(day10, part2, formula)]
-fn part2_f(input: &(Vec<Vec<Tile>>, Pos)) -> u32 {
-    let (map_input, start) = input;
-    let map = &mut map_input.clone();
-    let start_tile = map.get_mut(start.y as usize - 1).unwrap().get_mut(start.x as usize - 1).unwrap();
-    start_tile.connect_start(map_input);
-
-    let start_tile = get_tile(map, *start);
-
-    let mut lasts = (*start, *start);
-    let mut nexts = start_tile.neighbours.unwrap();
-    let mut vertices = vec![*start];
-    let mut boundary_length = 1;
-    // Check if we close the loop
-    while nexts.0 != nexts.1 {
-        // find next
-        let pos = nexts;
-        let tile0 = get_tile(map, pos.0);
-        let tile1 = get_tile(map, pos.1);
-
-        // Only the corners are needed to calculate the area with Shoelace formula
-        if tile0.corner {
-            vertices.insert(0, nexts.0);
-        }
-        if tile1.corner {
-            vertices.push(nexts.1);
-        }
-        boundary_length += 2;
-
-        let new_nexts = (tile0.next(lasts.0), tile1.next(lasts.1));
-        lasts = nexts;
-        nexts = new_nexts;
-    }
-    let tile = get_tile(map, nexts.1);
-    if tile.corner {
-        vertices.push(nexts.1);
-    }
-    boundary_length += 1;
+fn part2_f(maze: &Maze) -> Option<usize> {
+    let maze_loop = maze.find_loop()?;
+    let vertices = &maze_loop.path;
 
     // Shoelace formula
     let mut area = 0;
     let mut j = vertices.last().unwrap();
-    for pos in &vertices {
-        area += (j.x as i32 + pos.x as i32) * (j.y as i32 - pos.y as i32);
+    for pos in vertices {
+        area += (j.x as i64 + pos.x as i64) * (j.y as i64 - pos.y as i64);
         j = pos;
     }
-    let area = abs(area / 2);
+    let area = (area / 2).unsigned_abs() as usize;
 
     // Pick's theorem
-    (area - (boundary_length / 2) + 1) as u32
+    Some(area + 1 - vertices.len() / 2)
 }
 
 #[aoc(day10, part2, boundary_cross)]
-fn part2_bc(input: &(Vec<Vec<Tile>>, Pos)) -> u32 {
-    let (map_input, start) = input;
-    let map = &mut map_input.clone();
-    let start_tile = map.get_mut(start.y as usize - 1).unwrap().get_mut(start.x as usize - 1).unwrap();
-    start_tile.connect_start(map_input);
-
-    // Replace the char on the start tile by the correct letter for the connection
-    let (neighbour1, neighbour2) = start_tile.neighbours.unwrap();
-    start_tile.char = match (neighbour1.y as i8 - neighbour2.y as i8, neighbour1.x as i8 - neighbour2.x as i8) {
-        (-2, 0) => '|',
-        (0, -2) => '-',
-        (-1, -1) => '7',
-        (-1, 1) => 'F',
-        (1, 1) => 'L',
-        (1, -1) => 'J',
-        _ => unreachable!()
-    };
-    let start_tile = get_tile(map, *start);
-
-    let mut lasts = (*start, *start);
-    let mut nexts = start_tile.neighbours.unwrap();
-    let mut vertices = vec![*start];
-    // Check if we close the loop
-    while nexts.0 != nexts.1 {
-        // find next
-        let pos = nexts;
-        let tile0 = get_tile(map, pos.0);
-        let tile1 = get_tile(map, pos.1);
-
-        vertices.insert(0, nexts.0);
-        vertices.push(nexts.1);
-
-        let new_nexts = (tile0.next(lasts.0), tile1.next(lasts.1));
-        lasts = nexts;
-        nexts = new_nexts;
-    }
-    vertices.push(nexts.1);
-
-    // Calculate inside points based on when the boundary is crossed
-    let mut inside_points = 0;
-    for row in map {
-        let mut inside = false;
-        let mut c1 = None;
-        for tile in row {
-            if vertices.contains(&tile.pos) {
-                if tile.char == '|' {
-                    inside = !inside;
-                } else if tile.char == 'F' || tile.char == 'L' {
-                    c1 = Some(tile.char);
-                } else if c1.is_some() {
-                    let c = c1.unwrap();
-                    if (c == 'F' && tile.char == 'J') || (c == 'L' && tile.char == '7') {
-                        inside = !inside;
-                        c1 = None;
-                    }
-                }
-            } else if inside {
-                inside_points += 1;
-            }
-        }
-    }
-    inside_points
+fn part2_bc(maze: &Maze) -> Option<usize> {
+    let maze_loop = maze.find_loop()?;
+    let inside = maze.enclosed(&maze_loop);
+    Some(inside.iter().flatten().filter(|&&inside| inside).count())
 }
 
 
@@ -321,29 +234,97 @@ L7JLJL-JLJLJL--JLJ.L";
 
     #[test]
     fn part1_example_1() {
-        assert_eq!(part1(&parse(EXAMPLE_1_PART_1)), 4);
+        assert_eq!(part1(&parse(EXAMPLE_1_PART_1)), Some(4));
     }
 
     #[test]
     fn part1_example_2() {
-        assert_eq!(part1(&parse(EXAMPLE_2_PART_1)), 8);
+        assert_eq!(part1(&parse(EXAMPLE_2_PART_1)), Some(8));
     }
 
     #[test]
     fn part2_example_1() {
-        assert_eq!(part2_f(&parse(EXAMPLE_1_PART_2)), 4);
-        assert_eq!(part2_bc(&parse(EXAMPLE_1_PART_2)), 4);
+        assert_eq!(part2_f(&parse(EXAMPLE_1_PART_2)), Some(4));
+        assert_eq!(part2_bc(&parse(EXAMPLE_1_PART_2)), Some(4));
     }
 
     #[test]
     fn part2_example_2() {
-        assert_eq!(part2_f(&parse(EXAMPLE_2_PART_2)), 8);
-        assert_eq!(part2_bc(&parse(EXAMPLE_2_PART_2)), 8);
+        assert_eq!(part2_f(&parse(EXAMPLE_2_PART_2)), Some(8));
+        assert_eq!(part2_bc(&parse(EXAMPLE_2_PART_2)), Some(8));
     }
 
     #[test]
     fn part2_example_3() {
-        assert_eq!(part2_f(&parse(EXAMPLE_3_PART_2)), 10);
-        assert_eq!(part2_bc(&parse(EXAMPLE_3_PART_2)), 10);
+        assert_eq!(part2_f(&parse(EXAMPLE_3_PART_2)), Some(10));
+        assert_eq!(part2_bc(&parse(EXAMPLE_3_PART_2)), Some(10));
+    }
+
+    #[test]
+    fn start_tile() {
+        assert_eq!(parse(EXAMPLE_1_PART_1).find_loop().unwrap().start_tile, 'F');
+        assert_eq!(parse(EXAMPLE_2_PART_1).find_loop().unwrap().start_tile, 'F');
+        assert_eq!(parse(EXAMPLE_3_PART_2).find_loop().unwrap().start_tile, '7');
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn junk_around_start() {
+        // The pipes north and west of the start link to it but lead nowhere
+        let maze = parse("..|..
+.-S-7
+..|.|
+..L-J");
+        let maze_loop = maze.find_loop().unwrap();
+        assert_eq!(maze_loop.start_tile, 'F');
+        assert_eq!(maze_loop.path.len(), 8);
+        assert_eq!(maze_loop.path[1], Pos::new(1, 3));
+        assert_eq!(part1(&maze), Some(4));
+        assert_eq!(part2_f(&maze), Some(1));
+        assert_eq!(part2_bc(&maze), Some(1));
+        assert!(maze.enclosed(&maze_loop)[2][3]);
+
+        // A pipe that points at the loop without linking back doesn't break it
+        let maze = parse(".|...
+.S-7.
+.|.|.
+.L-J-
+...|.");
+        assert_eq!(part1(&maze), Some(4));
+    }
+
+    #[test]
+    fn no_loop() {
+        let maze = parse(".....
+.S-7.
+.|...
+.L-J.");
+        assert!(maze.find_loop().is_none());
+        assert_eq!(part1(&maze), None);
+    }
+
+    #[test]
+    fn large_grid() {
+        let (height, width) = (300, 400);
+        let mut input = String::new();
+        for y in 0..height {
+            for x in 0..width {
+                input.push(match (y, x) {
+                    (0, 0) => 'S',
+                    (0, x) if x == width - 1 => '7',
+                    (y, 0) if y == height - 1 => 'L',
+                    (y, x) if y == height - 1 && x == width - 1 => 'J',
+                    (0, _) => '-',
+                    (y, _) if y == height - 1 => '-',
+                    (_, 0) => '|',
+                    (_, x) if x == width - 1 => '|',
+                    _ => '.',
+                });
+            }
+            input.push('\n');
+        }
+        let maze = parse(&input);
+        assert_eq!(part1(&maze), Some(height + width - 2));
+        assert_eq!(part2_f(&maze), Some((height - 2) * (width - 2)));
+        assert_eq!(part2_bc(&maze), Some((height - 2) * (width - 2)));
+    }
+}
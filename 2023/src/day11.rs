@@ -1,103 +1,77 @@
 use aoc_runner_derive::{aoc, aoc_generator};
 
 struct Pos {
-    y: u32,
-    x: u32
+    y: usize,
+    x: usize
 }
 
 impl Pos {
-    fn new(y: u32, x: u32) -> Pos {
+    fn new(y: usize, x: usize) -> Pos {
         Pos {y, x}
     }
-
-    fn distance(&self, other: &Pos) -> u64 {
-        let y_diff = if self.y > other.y {self.y - other.y} else {other.y - self.y};
-        let x_diff = if self.x > other.x {self.x - other.x} else {other.x - self.x};
-        y_diff as u64 + x_diff as u64
-    }
 }
 
-fn parse(input: &str, expansion_factor: u32) -> Vec<Pos> {
-    let insert_space = expansion_factor - 1;
-    let mut galaxy_pos: Vec<Pos> = input.lines().enumerate()
+#[aoc_generator(day11)]
+fn parse(input: &str) -> Vec<Pos> {
+    input.lines().enumerate()
         .flat_map(|(y, line)|
             line.chars().enumerate()
                 .filter(|(_, c)| *c == '#')
-                .map(|(x, _)| Pos::new(y as u32, x as u32))
-                .collect::<Vec<Pos>>()
-        ).collect();
-
-    let mut y_size = galaxy_pos.iter().max_by_key(|pos| pos.y).unwrap().y;
-    let mut x_size = galaxy_pos.iter().max_by_key(|pos| pos.x).unwrap().x;
-
-    // Expand galaxy
-    // Y Expand
-    let mut y = 0;
-    while y < y_size {
-        y += 1;
-        // Check for galaxy on y
-        let expand_needed = !galaxy_pos.iter().any(|pos| pos.y == y);
-        if expand_needed {
-            // Expand galaxy
-            galaxy_pos.iter_mut()
-                .filter(|pos| pos.y > y)
-                .for_each(|pos| pos.y += insert_space);
-
-            y_size += insert_space;
-            y += insert_space;
-        }
+                .map(move |(x, _)| Pos::new(y, x))
+        ).collect()
+}
+
+// Every empty line before a coordinate is replaced by `expansion_factor` lines, at least one
+fn expand(coordinates: &[usize], expansion_factor: u64) -> Vec<u64> {
+    assert!(expansion_factor > 0, "Empty lines can't disappear, the expansion factor has to be at least 1");
+    let size = coordinates.iter().max().map_or(0, |&max| max + 1);
+    let mut occupied = vec![false; size];
+    for &coordinate in coordinates {
+        occupied[coordinate] = true;
     }
 
-    // X Expand
-    let mut x = 0;
-    while x < x_size {
-        x += 1;
-        // Check for galaxy on x
-        let expand_needed = !galaxy_pos.iter().any(|pos| pos.x == x);
-        if expand_needed {
-            // Expand galaxy
-            galaxy_pos.iter_mut()
-                .filter(|pos| pos.x > x)
-                .for_each(|pos| pos.x += insert_space);
-
-            x_size += insert_space;
-            x += insert_space;
+    // Prefix sum of the empty lines before every coordinate
+    let mut empty_before = Vec::with_capacity(size);
+    let mut empty = 0;
+    for occupied in occupied {
+        empty_before.push(empty);
+        if !occupied {
+            empty += 1;
         }
     }
 
-    galaxy_pos
+    coordinates.iter()
+        .map(|&coordinate| coordinate as u64 + empty_before[coordinate] * (expansion_factor - 1))
+        .collect()
 }
 
-#[aoc_generator(day11, part1)]
-fn parse1(input: &str) -> Vec<Pos> {
-    parse(input, 2)
-}
-
-#[aoc_generator(day11, part2)]
-fn parse2(input: &str) -> Vec<Pos> {
-    parse(input, 1_000_000)
+// Sum of |a - b| over all pairs, once sorted every value is larger than all values before it
+fn pairwise_distance(mut values: Vec<u64>) -> u64 {
+    values.sort_unstable();
+    let mut prefix = 0;
+    let mut total = 0;
+    for (i, value) in values.into_iter().enumerate() {
+        total += value * i as u64 - prefix;
+        prefix += value;
+    }
+    total
 }
 
-#[inline]
-fn solve(input: &[Pos]) -> u64 {
-    // input.iter().combinations(2)
-    //     .map(|comb| comb.first().unwrap().distance(comb.last().unwrap()))
-    //     .sum()
-    // The following does the exact same thing, but is a lot more efficient
-    input.iter().enumerate()
-        .map(|(i, pos1)|
-            input[0..i].iter().map(|pos2| pos1.distance(pos2)).sum::<u64>()
-        ).sum()
+// The Manhattan distance splits per axis, so both axes are summed separately
+fn solve(galaxies: &[Pos], expansion_factor: u64) -> u64 {
+    let ys: Vec<usize> = galaxies.iter().map(|pos| pos.y).collect();
+    let xs: Vec<usize> = galaxies.iter().map(|pos| pos.x).collect();
+    pairwise_distance(expand(&ys, expansion_factor)) + pairwise_distance(expand(&xs, expansion_factor))
 }
 
 #[aoc(day11, part1)]
 fn part1(input: &[Pos]) -> u64 {
-    solve(input)
+    solve(input, 2)
 }
 
 #[aoc(day11, part2)]
 fn part2(input: &[Pos]) -> u64 {
-    solve(input)
+    solve(input, 1_000_000)
 }
 
 
@@ -118,12 +92,41 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse1(EXAMPLE)), 374);
+        assert_eq!(part1(&parse(EXAMPLE)), 374);
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE, 10)), 1030);
-        assert_eq!(part2(&parse(EXAMPLE, 100)), 8410);
+        assert_eq!(solve(&parse(EXAMPLE), 10), 1030);
+        assert_eq!(solve(&parse(EXAMPLE), 100), 8410);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn all_pairs() {
+        let galaxies = parse(EXAMPLE);
+        let ys = expand(&galaxies.iter().map(|pos| pos.y).collect::<Vec<_>>(), 7);
+        let xs = expand(&galaxies.iter().map(|pos| pos.x).collect::<Vec<_>>(), 7);
+        let mut expected = 0;
+        for i in 0..galaxies.len() {
+            for j in 0..i {
+                expected += ys[i].abs_diff(ys[j]) + xs[i].abs_diff(xs[j]);
+            }
+        }
+        assert_eq!(solve(&galaxies, 7), expected);
+    }
+
+    #[test]
+    fn huge_expansion() {
+        // Every empty line adds the same amount of distance for each extra line it is replaced by
+        let galaxies = parse(EXAMPLE);
+        let per_line = solve(&galaxies, 2) - solve(&galaxies, 1);
+        let factor = 1_000_000_000_000;
+        assert_eq!(solve(&galaxies, factor), solve(&galaxies, 1) + per_line * (factor - 1));
+    }
+
+    #[test]
+    #[should_panic]
+    fn no_expansion_factor() {
+        solve(&parse(EXAMPLE), 0);
+    }
+}
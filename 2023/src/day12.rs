@@ -30,18 +30,18 @@ fn parse(input: &str, part2: bool) -> Vec<Record> {
 //     parse(input, true)
 // }
 
-struct Record {
+pub struct Record {
     springs: Box<[u8]>,
     conditions: Box<[u8]>,
     memoization: FxHashMap<(u8, u8), u64>,
-    count_no_working: u8,
-    sum_conditions: u8
+    count_no_working: usize,
+    sum_conditions: usize
 }
 
 impl Record {
-    fn new(springs: Box<[u8]>, conditions: Box<[u8]>) -> Record {
-        let count_no_working = springs.iter().filter(|&&char| char != b'.').count() as u8;
-        let sum_conditions = conditions.iter().sum();
+    pub fn new(springs: Box<[u8]>, conditions: Box<[u8]>) -> Record {
+        let count_no_working = springs.iter().filter(|&&char| char != b'.').count();
+        let sum_conditions = conditions.iter().map(|&condition| condition as usize).sum();
         Record {springs, conditions, memoization: FxHashMap::default(), count_no_working, sum_conditions}
     }
 
@@ -53,7 +53,7 @@ impl Record {
             // Linear unroll to avoid recursive calls
             return self.springs[springs_index as usize..].iter().all(|&char| char != b'#') as u64
         }
-        if self.count_no_working - placed_working < self.sum_conditions {
+        if self.count_no_working - (placed_working as usize) < self.sum_conditions {
             // Count_no_working = amount of # and ? in the string (precomputed)
             // Placed working is increased every time a ? is replaced with .
             // Sum_conditions is the sum of numbers at the end of the line, also pre computed
@@ -106,6 +106,219 @@ impl Record {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cell {
+    Broken,
+    Working,
+    Unknown,
+}
+
+// Ways to fill the springs before index i with the first j groups, counted from the front and from the back.
+// The springs get an extra . at the end so every group can be placed together with the . that follows it.
+// Long lines easily have more arrangements than fit in any integer, so the counts saturate. A saturated count is
+// never zero, which is all the line solver needs.
+struct Tables {
+    springs: Vec<u8>,
+    forward: Vec<Vec<u128>>,
+    backward: Vec<Vec<u128>>,
+}
+
+impl Record {
+    fn fits(springs: &[u8], from: usize, size: usize) -> bool {
+        from + size < springs.len()
+            && springs[from..from + size].iter().all(|&spring| spring != b'.')
+            && springs[from + size] != b'#'
+    }
+
+    fn tables(&self) -> Tables {
+        let mut springs = self.springs.to_vec();
+        springs.push(b'.');
+        let (length, groups) = (springs.len(), self.conditions.len());
+
+        let mut forward = vec![vec![0u128; groups + 1]; length + 1];
+        forward[0][0] = 1;
+        for i in 0..length {
+            for j in 0..=groups {
+                let ways = forward[i][j];
+                if ways == 0 {
+                    continue;
+                }
+                if springs[i] != b'#' {
+                    forward[i + 1][j] = forward[i + 1][j].saturating_add(ways);
+                }
+                if j < groups && Self::fits(&springs, i, self.conditions[j] as usize) {
+                    let next = &mut forward[i + self.conditions[j] as usize + 1][j + 1];
+                    *next = next.saturating_add(ways);
+                }
+            }
+        }
+
+        let mut backward = vec![vec![0u128; groups + 1]; length + 1];
+        backward[length][groups] = 1;
+        for i in (0..length).rev() {
+            for j in 0..=groups {
+                if springs[i] != b'#' {
+                    backward[i][j] = backward[i][j].saturating_add(backward[i + 1][j]);
+                }
+                if j < groups && Self::fits(&springs, i, self.conditions[j] as usize) {
+                    backward[i][j] = backward[i][j].saturating_add(backward[i + self.conditions[j] as usize + 1][j + 1]);
+                }
+            }
+        }
+
+        Tables { springs, forward, backward }
+    }
+
+    // Amount of arrangements, the same as solve but for springs of any length, saturates at u128::MAX
+    pub fn count(&self) -> u128 {
+        self.tables().backward[0][0]
+    }
+
+    // Whether every arrangement agrees on a spring, None when there are no arrangements at all
+    pub fn analyse(&self) -> Option<Vec<Cell>> {
+        let Tables { springs, forward, backward } = self.tables();
+        if backward[0][0] == 0 {
+            return None;
+        }
+
+        // Only the steps that are part of some arrangement matter. A spring can be broken when such a group covers
+        // it, the covering groups are added as ranges on a difference array.
+        let mut covering = vec![0isize; springs.len() + 1];
+        let mut can_work = vec![false; springs.len()];
+        for (i, row) in forward.iter().enumerate().take(springs.len()) {
+            for (j, &ways) in row.iter().enumerate() {
+                if ways == 0 {
+                    continue;
+                }
+                if springs[i] != b'#' && backward[i + 1][j] != 0 {
+                    can_work[i] = true;
+                }
+                let Some(&size) = self.conditions.get(j) else { continue };
+                let size = size as usize;
+                if Self::fits(&springs, i, size) && backward[i + size + 1][j + 1] != 0 {
+                    covering[i] += 1;
+                    covering[i + size] -= 1;
+                    // The . that closes the group
+                    can_work[i + size] = true;
+                }
+            }
+        }
+
+        let mut running = 0;
+        let cells = covering.iter().zip(can_work).take(self.springs.len())
+            .map(|(&difference, can_work)| {
+                running += difference;
+                match (running > 0, can_work) {
+                    (true, true) => Cell::Unknown,
+                    (true, false) => Cell::Broken,
+                    _ => Cell::Working,
+                }
+            })
+            .collect();
+        Some(cells)
+    }
+
+    // Every arrangement written out, only meant for debugging small records
+    pub fn arrangements(&self) -> Vec<String> {
+        fn build(record: &Record, tables: &Tables, i: usize, j: usize, current: &mut Vec<u8>, result: &mut Vec<String>) {
+            if tables.backward[i][j] == 0 {
+                return;
+            }
+            if i == tables.springs.len() {
+                result.push(String::from_utf8_lossy(&current[..record.springs.len()]).into_owned());
+                return;
+            }
+            if tables.springs[i] != b'#' {
+                current.push(b'.');
+                build(record, tables, i + 1, j, current, result);
+                current.pop();
+            }
+            if j < record.conditions.len() && Record::fits(&tables.springs, i, record.conditions[j] as usize) {
+                let size = record.conditions[j] as usize;
+                current.extend(std::iter::repeat_n(b'#', size));
+                current.push(b'.');
+                build(record, tables, i + size + 1, j + 1, current, result);
+                current.truncate(current.len() - size - 1);
+            }
+        }
+
+        let tables = self.tables();
+        let mut result = vec![];
+        build(self, &tables, 0, 0, &mut vec![], &mut result);
+        result
+    }
+}
+
+// A grid where every row and column has to match its groups of filled cells
+pub struct Nonogram {
+    rows: Vec<Vec<u8>>,
+    columns: Vec<Vec<u8>>,
+}
+
+impl Nonogram {
+    pub fn new(rows: Vec<Vec<u8>>, columns: Vec<Vec<u8>>) -> Self {
+        Nonogram { rows, columns }
+    }
+
+    // Filled cells are #, every line is solved on its own until nothing changes and then a cell is guessed
+    pub fn solve(&self) -> Option<Vec<String>> {
+        let grid = vec![vec![b'?'; self.columns.len()]; self.rows.len()];
+        let grid = self.search(grid)?;
+        Some(grid.into_iter().map(|row| String::from_utf8(row).unwrap()).collect())
+    }
+
+    fn search(&self, mut grid: Vec<Vec<u8>>) -> Option<Vec<Vec<u8>>> {
+        self.propagate(&mut grid)?;
+
+        let unknown = (0..self.rows.len())
+            .flat_map(|y| (0..self.columns.len()).map(move |x| (y, x)))
+            .find(|&(y, x)| grid[y][x] == b'?');
+        let Some((y, x)) = unknown else {
+            return Some(grid);
+        };
+        [b'#', b'.'].into_iter().find_map(|guess| {
+            let mut grid = grid.clone();
+            grid[y][x] = guess;
+            self.search(grid)
+        })
+    }
+
+    // Fills in every cell that is forced by its row or column, None on a contradiction
+    fn propagate(&self, grid: &mut [Vec<u8>]) -> Option<()> {
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for (y, groups) in self.rows.iter().enumerate() {
+                let line = grid[y].clone();
+                for (x, spring) in Self::solve_line(&line, groups)?.into_iter().enumerate() {
+                    changed |= grid[y][x] != spring;
+                    grid[y][x] = spring;
+                }
+            }
+            for (x, groups) in self.columns.iter().enumerate() {
+                let line: Vec<u8> = grid.iter().map(|row| row[x]).collect();
+                for (y, spring) in Self::solve_line(&line, groups)?.into_iter().enumerate() {
+                    changed |= grid[y][x] != spring;
+                    grid[y][x] = spring;
+                }
+            }
+        }
+        Some(())
+    }
+
+    fn solve_line(line: &[u8], groups: &[u8]) -> Option<Vec<u8>> {
+        let record = Record::new(line.into(), groups.into());
+        let cells = record.analyse()?;
+        Some(cells.into_iter()
+            .map(|cell| match cell {
+                Cell::Broken => b'#',
+                Cell::Working => b'.',
+                Cell::Unknown => b'?',
+            })
+            .collect())
+    }
+}
+
 #[aoc(day12, part1)]
 fn part1(input: &str) -> u64 {
     let mut input = parse(input, false);
@@ -143,4 +356,86 @@ mod tests {
     fn part2_example() {
         assert_eq!(part2(&EXAMPLE), 525152);
     }
+
+    #[test]
+    fn line_solver() {
+        let records = parse(EXAMPLE, false);
+        for mut record in parse(EXAMPLE, false) {
+            let count = record.solve(0, 0, 0);
+            assert_eq!(record.count(), count as u128);
+            assert_eq!(record.arrangements().len() as u64, count);
+        }
+        for record in parse(EXAMPLE, true) {
+            assert_eq!(record.count(), Record::new(record.springs.clone(), record.conditions.clone()).solve(0, 0, 0) as u128);
+        }
+
+        use Cell::*;
+        assert_eq!(records[0].analyse(), Some(vec![Broken, Working, Broken, Working, Broken, Broken, Broken]));
+        assert_eq!(records[0].arrangements(), ["#.#.###"]);
+        let cells = records[5].analyse().unwrap();
+        assert_eq!(cells[..5], [Working, Broken, Broken, Broken, Working]);
+        assert!(cells[5..].iter().all(|&cell| cell == Unknown));
+
+        let impossible = Record::new(b"#.#".as_slice().into(), [3].into());
+        assert_eq!(impossible.analyse(), None);
+        assert!(impossible.arrangements().is_empty());
+        let empty = Record::new(b"?.?".as_slice().into(), [].into());
+        assert_eq!(empty.analyse(), Some(vec![Working; 3]));
+    }
+
+    #[test]
+    fn long_lines() {
+        // C(76, 25) arrangements don't fit in a u64
+        let record = Record::new(vec![b'?'; 100].into(), vec![1; 25].into());
+        assert_eq!(record.count(), 78_367_246_720_143_449_328);
+        assert_eq!(record.analyse(), Some(vec![Cell::Unknown; 100]));
+
+        let record = Record::new(vec![b'?'; 602].into(), vec![200, 200, 198].into());
+        let cells = record.analyse().unwrap();
+        assert!(cells[2..200].iter().all(|&cell| cell == Cell::Broken));
+        assert_eq!(cells[0], Cell::Unknown);
+        assert_eq!(record.count(), 10);
+
+        // Far too many arrangements for any integer, the counts saturate but the analysis stays exact
+        let record = Record::new(vec![b'?'; 1000].into(), vec![1; 200].into());
+        assert_eq!(record.count(), u128::MAX);
+        assert_eq!(record.analyse(), Some(vec![Cell::Unknown; 1000]));
+    }
+
+    fn groups(line: impl Iterator<Item = u8>) -> Vec<u8> {
+        line.collect::<Vec<u8>>()
+            .split(|&cell| cell == b'.')
+            .filter(|group| !group.is_empty())
+            .map(|group| group.len() as u8)
+            .collect()
+    }
+
+    #[test]
+    fn nonogram() {
+        let heart = Nonogram::new(
+            vec![vec![1, 1], vec![5], vec![5], vec![3], vec![1]],
+            vec![vec![2], vec![4], vec![4], vec![4], vec![2]],
+        );
+        assert_eq!(heart.solve().unwrap(), [".#.#.", "#####", "#####", ".###.", "..#.."]);
+
+        // Line solving alone gets nowhere here, there are two solutions
+        let diagonal = Nonogram::new(vec![vec![1], vec![1]], vec![vec![1], vec![1]]);
+        let solution = diagonal.solve().unwrap();
+        for (y, row) in solution.iter().enumerate() {
+            assert_eq!(groups(row.bytes()), diagonal.rows[y]);
+        }
+        for x in 0..2 {
+            assert_eq!(groups(solution.iter().map(|row| row.as_bytes()[x])), diagonal.columns[x]);
+        }
+
+        // Every row starts out all unknown with 50 groups of 1, the columns fix where they go
+        let width = 100;
+        let columns: Vec<Vec<u8>> = (0..width).map(|x| if x % 2 == 0 { vec![3] } else { vec![] }).collect();
+        let wide = Nonogram::new(vec![vec![1; 50]; 3], columns);
+        let row: String = (0..width).map(|x| if x % 2 == 0 { '#' } else { '.' }).collect();
+        assert_eq!(wide.solve().unwrap(), [row.clone(), row.clone(), row]);
+
+        let impossible = Nonogram::new(vec![vec![2], vec![]], vec![vec![1], vec![2]]);
+        assert_eq!(impossible.solve(), None);
+    }
 }
\ No newline at end of file
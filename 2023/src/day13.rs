@@ -1,65 +1,124 @@
-use std::cmp::min;
-use std::iter::zip;
 use aoc_runner_derive::{aoc, aoc_generator};
 
-#[aoc_generator(day13)]
-fn parse(input: &str) -> Vec<Vec<Vec<char>>> {
-    let mut result = vec![];
-    for grid in input.split("\n\n") {
-        result.push(grid.lines().map(|line| line.chars().collect()).collect());
+// A row or column as a bitmask split over words, bit i is set when the i-th cell is a rock
+type Line = Vec<u64>;
+
+fn set(line: &mut Line, index: usize) {
+    if line.len() <= index / 64 {
+        line.resize(index / 64 + 1, 0);
     }
-    result
+    line[index / 64] |= 1 << (index % 64);
+}
+
+// XOR of two lines word by word, lines are padded with zero words to the same length
+fn differences<'a>(a: &'a Line, b: &'a Line) -> impl Iterator<Item = u64> + 'a {
+    (0..a.len().max(b.len())).map(|word| a.get(word).unwrap_or(&0) ^ b.get(word).unwrap_or(&0))
 }
 
-fn transpose(original: &Vec<Vec<char>>) -> Vec<Vec<char>> {
-    assert!(!original.is_empty());
-    let mut transposed: Vec<Vec<char>> = (0..original[0].len()).map(|_| vec![]).collect();
+pub struct Pattern {
+    rows: Vec<Line>,
+    columns: Vec<Line>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Axis {
+    // Mirror between two rows
+    Horizontal,
+    // Mirror between two columns
+    Vertical,
+}
 
-    original.iter().for_each(|original_row| {
-        original_row.iter().zip(&mut transposed)
-            .for_each(|(item, transposed_row)| transposed_row.push(*item))
-    });
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Reflection {
+    pub axis: Axis,
+    // Amount of rows above or columns left of the mirror
+    pub line: usize,
+    // (y, x) of the cells that have to be flipped, the cell before the mirror is reported
+    pub smudges: Vec<(usize, usize)>,
+}
 
-    transposed
+impl Reflection {
+    pub fn score(&self) -> usize {
+        match self.axis {
+            Axis::Horizontal => 100 * self.line,
+            Axis::Vertical => self.line,
+        }
+    }
 }
 
-fn solve(grid: &Vec<Vec<char>>, factor: u32, mistakes_allowed: u32) -> u32 {
-    // Find the rows where the mirror is in between
-    let x_len = grid.first().unwrap().len();
-    for (row1, row2) in zip(0..grid.len()-1, 1..grid.len()) {
-        let max_offset = min(row1 + 1, grid.len() - row2);
+impl Pattern {
+    fn new(grid: &str) -> Pattern {
+        let width = grid.lines().map(str::len).max().unwrap_or(0);
+        let height = grid.lines().count();
+        let mut rows = vec![vec![]; height];
+        let mut columns = vec![vec![]; width];
+        for (y, line) in grid.lines().enumerate() {
+            for (x, cell) in line.bytes().enumerate() {
+                if cell == b'#' {
+                    set(&mut rows[y], x);
+                    set(&mut columns[x], y);
+                }
+            }
+        }
+        Pattern { rows, columns }
+    }
 
-        let smudge: usize = (0..x_len).map(|x| {
-            (0..max_offset).filter(|y| grid[row1 - y][x] != grid[row2 + y][x]).count()
-        }).sum();
+    // First mirror in the lines with exactly `smudges` differing cells, as the line index and the differences
+    fn mirror(lines: &[Line], smudges: u32) -> Option<(usize, Vec<(usize, usize)>)> {
+        (1..lines.len()).find_map(|line| {
+            let pairs = (0..line).rev().zip(line..lines.len());
+            let count: u32 = pairs.clone()
+                .map(|(a, b)| differences(&lines[a], &lines[b]).map(u64::count_ones).sum::<u32>())
+                .sum();
+            if count != smudges {
+                return None;
+            }
+            let cells = pairs
+                .flat_map(|(a, b)| {
+                    differences(&lines[a], &lines[b]).enumerate().flat_map(move |(word, difference)| {
+                        (0..64).filter(move |bit| difference >> bit & 1 == 1).map(move |bit| (a, word * 64 + bit))
+                    })
+                })
+                .collect();
+            Some((line, cells))
+        })
+    }
 
-        if smudge as u32 == mistakes_allowed {
-            // + 1 bc the puzzle is not zero indexed
-            return factor * (row1 + 1) as u32
+    pub fn reflection(&self, smudges: u32) -> Option<Reflection> {
+        if let Some((line, cells)) = Self::mirror(&self.rows, smudges) {
+            return Some(Reflection { axis: Axis::Horizontal, line, smudges: cells });
         }
+        let (line, cells) = Self::mirror(&self.columns, smudges)?;
+        let smudges = cells.into_iter().map(|(x, y)| (y, x)).collect();
+        Some(Reflection { axis: Axis::Vertical, line, smudges })
     }
-    0
 }
 
-#[aoc(day13, part1)]
-fn part1(input: &[Vec<Vec<char>>]) -> u32 {
-    let transposed: Vec<Vec<Vec<char>>> = input.iter().map(transpose).collect();
+#[aoc_generator(day13)]
+fn parse(input: &str) -> Vec<Pattern> {
+    input.split("\n\n").map(Pattern::new).collect()
+}
 
-    input.iter().map(|grid| solve(grid, 100, 0)).sum::<u32>()
-        + transposed.iter().map(|grid| solve(grid, 1, 0)).sum::<u32>()
+fn solve(input: &[Pattern], smudges: u32) -> Option<usize> {
+    input.iter()
+        .map(|pattern| pattern.reflection(smudges).map(|reflection| reflection.score()))
+        .sum()
 }
 
-#[aoc(day13, part2)]
-fn part2(input: &[Vec<Vec<char>>]) -> u32 {
-    let transposed: Vec<Vec<Vec<char>>> = input.iter().map(transpose).collect();
+#[aoc(day13, part1)]
+fn part1(input: &[Pattern]) -> Option<usize> {
+    solve(input, 0)
+}
 
-    input.iter().map(|grid| solve(grid, 100, 1)).sum::<u32>()
-        + transposed.iter().map(|grid| solve(grid, 1, 1)).sum::<u32>()
+#[aoc(day13, part2)]
+fn part2(input: &[Pattern]) -> Option<usize> {
+    solve(input, 1)
 }
 
 
 #[cfg(test)]
 mod tests {
+    use itertools::Itertools;
     use super::*;
 
     const EXAMPLE: &str = "#.##..##.
@@ -80,11 +139,50 @@ mod tests {
 
     #[test]
     fn part1_example() {
-        assert_eq!(part1(&parse(EXAMPLE)), 405);
+        assert_eq!(part1(&parse(EXAMPLE)), Some(405));
     }
 
     #[test]
     fn part2_example() {
-        assert_eq!(part2(&parse(EXAMPLE)), 400);
+        assert_eq!(part2(&parse(EXAMPLE)), Some(400));
+    }
+
+    #[test]
+    fn reflections() {
+        let patterns = parse(EXAMPLE);
+        assert_eq!(patterns[0].reflection(0), Some(Reflection { axis: Axis::Vertical, line: 5, smudges: vec![] }));
+        assert_eq!(patterns[1].reflection(0), Some(Reflection { axis: Axis::Horizontal, line: 4, smudges: vec![] }));
+        assert_eq!(patterns[0].reflection(1), Some(Reflection { axis: Axis::Horizontal, line: 3, smudges: vec![(0, 0)] }));
+        assert_eq!(patterns[1].reflection(1), Some(Reflection { axis: Axis::Horizontal, line: 1, smudges: vec![(0, 4)] }));
+    }
+
+    #[test]
+    fn more_smudges() {
+        let pattern = Pattern::new("#.#
+...
+.##");
+        assert_eq!(pattern.reflection(0), None);
+        let reflection = pattern.reflection(2).unwrap();
+        assert_eq!(reflection, Reflection { axis: Axis::Horizontal, line: 1, smudges: vec![(0, 0), (0, 2)] });
+        assert_eq!(reflection.score(), 100);
+        assert_eq!(pattern.reflection(1), Some(Reflection { axis: Axis::Vertical, line: 2, smudges: vec![(0, 1)] }));
+    }
+
+    #[test]
+    fn large_patterns() {
+        // 100 wide and 150 high, mirrored after column 70 and row 90 with one smudge in the far corner
+        let mut grid: Vec<Vec<u8>> = (0..150)
+            .map(|y| (0..100).map(|x| if (y * 7 + x * 3) % 5 == 0 { b'#' } else { b'.' }).collect())
+            .collect();
+        for row in grid.iter_mut() {
+            for x in 70..100 {
+                row[x] = row[139 - x];
+            }
+        }
+        let pattern = |grid: &[Vec<u8>]| Pattern::new(&grid.iter().map(|row| String::from_utf8(row.clone()).unwrap()).join("\n"));
+        assert_eq!(pattern(&grid).reflection(0), Some(Reflection { axis: Axis::Vertical, line: 70, smudges: vec![] }));
+
+        grid[149][40] = if grid[149][40] == b'#' { b'.' } else { b'#' };
+        assert_eq!(pattern(&grid).reflection(1), Some(Reflection { axis: Axis::Vertical, line: 70, smudges: vec![(149, 40)] }));
     }
-}
\ No newline at end of file
+}
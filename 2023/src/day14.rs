@@ -1,5 +1,6 @@
 use aoc_runner_derive::aoc;
-use rustc_hash::FxHashMap;
+
+use crate::cycle;
 
 #[derive(Debug, Eq, PartialEq, Hash, Clone)]
 enum Rock {
@@ -142,42 +143,44 @@ fn part1(input: &str) -> u32 {
     score(&grid, y_len)
 }
 
-#[aoc(day14, part2)]
-fn part2(input: &str) -> u32 {
-    let (mut grid, (y_len, x_len)) = parse(input);
+// North, West, South, East, the grid is back in its original orientation afterwards
+fn spin(grid: &mut Vec<Vec<Rock>>, y_len: u8, x_len: u8) {
+    for (b, len) in [(false, y_len), (false, x_len), (true, y_len), (true, x_len)] {
+        till(grid, b, len);
 
-    let mut cache = FxHashMap::default();
-
-    let grid_clone: Vec<Vec<Rock>> = grid.clone();
-    let mut cycle = 0;
-    cache.insert(grid_clone, cycle);
-    let (cycle_start, cycle_end) = loop {
-        // North, West, South, East
-        for (b, len) in [(false, y_len), (false, x_len), (true, y_len), (true, x_len)] {
-            till(&mut grid, b, len);
+        // Change column and row
+        transpose(grid, len);
+    }
+}
 
-            // Change column and row
-            transpose(&mut grid, len);
-        }
-        cycle += 1;
-        if let Some(cycle_start) = cache.get(&grid) {
-            break (cycle_start, cycle);
+// The cube rocks never move, so the positions of the round rocks as a bitset identify the grid
+fn key(grid: &[Vec<Rock>], y_len: u8) -> Vec<u64> {
+    let mut bits = vec![0u64; (grid.len() * y_len as usize).div_ceil(64)];
+    for (x, column) in grid.iter().enumerate() {
+        for rock in column {
+            if let Rock::Round(y) = rock {
+                let index = x * y_len as usize + *y as usize;
+                bits[index / 64] |= 1 << (index % 64);
+            }
         }
+    }
+    bits
+}
 
-        let grid_clone: Vec<Vec<Rock>> = grid.clone();
-        cache.insert(grid_clone, cycle);
-    };
-
-    let cycle_length = cycle_end - cycle_start;
-
-    let index = cycle_start + (1000000000 - cycle_start) % cycle_length;
+#[aoc(day14, part2)]
+fn part2(input: &str) -> u32 {
+    let (grid, (y_len, x_len)) = parse(input);
 
-    let (grid, _) = cache.iter().find(|(_, value)| **value == index).unwrap();
+    let grid = cycle::fast_forward(
+        grid,
+        |grid| spin(grid, y_len, x_len),
+        |grid| key(grid, y_len),
+        1000000000
+    );
 
-    score(grid, y_len)
+    score(&grid, y_len)
 }
 
-
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -202,4 +205,12 @@ O.#..O.#.#
     fn part2_example() {
         assert_eq!(part2(EXAMPLE), 64);
     }
+
+    #[test]
+    fn spin_cycle() {
+        let (grid, (y_len, x_len)) = parse(EXAMPLE);
+        let hashed = cycle::find(grid.clone(), |grid| spin(grid, y_len, x_len), |grid| key(grid, y_len));
+        assert_eq!(hashed, cycle::Cycle { prefix: 3, period: 7 });
+        assert_eq!(cycle::brent(&grid, |grid| spin(grid, y_len, x_len)), hashed);
+    }
 }
\ No newline at end of file
@@ -3,6 +3,8 @@ use std::ops::Add;
 use aoc_runner_derive::{aoc, aoc_generator};
 use rustc_hash::FxHashSet;
 
+use crate::polynomial;
+
 #[derive(Eq, PartialEq, Hash, Clone, Copy)]
 struct Point {
     y: i32,
@@ -131,50 +133,16 @@ fn part2_solver(iterations: u32, sequence_length: u8, rocks: &[Vec<u32>], start:
 
     let quotient = iterations.div_euclid(divisor) as i64;
 
-    // This code is to make the examples work bc they don't have the same nice properties as the
-    // actual input
-    // For the examples first part of the sequence is irregular so we don't use it to construct
-    // the polynomial
-    let i = 50u32.saturating_sub(remainder).div_ceil(divisor);
-    let quotient = quotient - i as i64;
-    sequence = sequence[i as usize..].to_vec();
-    // Till here
-
-    let sequence_length = sequence.len();
-    let mut sequence_depth = 0;
-    // let mut string = "".to_string();
-    // let mut prefix = "".to_string();
-    let mut result = 0;
-    let mut multiply = 1;
-    let mut factorial = 1;
-    while !sequence.iter().all(|&value| value == 0) {
-        if sequence_depth == 0 {
-            // string.push_str(sequence.first().unwrap().to_string().as_str());
-            result += *sequence.first().unwrap() as i64;
-        } else {
-            multiply *= quotient - (sequence_depth - 1);
-            factorial *= sequence_depth;
-            result += multiply * (*sequence.first().unwrap() as i64 / factorial);
-
-            // string.push_str(format!(" + (i - {}) {prefix} * ({} / {}!)", sequence_depth - 1,
-            //                         sequence.first().unwrap(), sequence_depth).as_str());
-            // prefix.push_str(format!("* (i - {})", sequence_depth - 1).as_str());
-        }
-        sequence_depth += 1;
-
-        // println!("{:?}", sequence);
-
-        let mut next_sequence = vec![];
-        for window in sequence.windows(2) {
-            next_sequence.push(window.last().unwrap() - window.first().unwrap());
-        }
-        sequence = next_sequence;
-    }
-    if sequence_length == sequence_depth as usize {
-        println!("Might have not enough datapoints to construct the correct polynomial")
+    // The first samples can be irregular while the diamond is still growing inside the first grids (the examples
+    // have this), so drop samples from the front until the rest is confirmed to follow a polynomial
+    let (skip, fit) = (0..sequence.len())
+        .map(|skip| (skip, polynomial::fit(&sequence[skip..])))
+        .find(|(_, fit)| !fit.underdetermined())
+        .unwrap_or_else(|| (0, polynomial::fit(&sequence)));
+    if fit.underdetermined() {
+        eprintln!("Might have not enough datapoints to construct the correct polynomial")
     }
-    // println!("{}", string);
-    result
+    fit.polynomial.value(quotient - skip as i64).try_into().unwrap()
 }
 
 #[aoc(day21, part2)]
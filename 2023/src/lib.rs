@@ -30,4 +30,7 @@ pub mod day23;
 pub mod day24;
 pub mod day25;
 
+pub mod cycle;
+pub mod polynomial;
+
 aoc_lib!{ year = 2023 }
\ No newline at end of file
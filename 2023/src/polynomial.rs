@@ -0,0 +1,134 @@
+use num::{BigInt, BigRational, One, Zero};
+
+// Coefficients from the constant term upwards
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Polynomial {
+    coefficients: Vec<BigRational>,
+}
+
+impl Polynomial {
+    pub fn coefficients(&self) -> &[BigRational] {
+        &self.coefficients
+    }
+
+    pub fn degree(&self) -> usize {
+        self.coefficients.iter().rposition(|c| !c.is_zero()).unwrap_or(0)
+    }
+
+    pub fn at(&self, x: &BigRational) -> BigRational {
+        // Horner's method
+        self.coefficients.iter().rev().fold(BigRational::zero(), |acc, c| acc * x + c)
+    }
+
+    // A polynomial fitted through integer samples has integer values at every integer
+    pub fn value(&self, x: i64) -> BigInt {
+        let value = self.at(&BigRational::from_integer(x.into()));
+        debug_assert!(value.is_integer());
+        value.to_integer()
+    }
+}
+
+pub struct Fit {
+    pub polynomial: Polynomial,
+    pub degree: usize,
+    pub samples: usize,
+}
+
+impl Fit {
+    // Without a sample left over to confirm the degree, any sequence fits and extrapolating is a guess
+    pub fn underdetermined(&self) -> bool {
+        self.degree + 1 >= self.samples
+    }
+}
+
+// Minimal degree polynomial p with p(i) = samples[i]
+pub fn fit<T: Into<BigInt> + Copy>(samples: &[T]) -> Fit {
+    // Leading entries of the forward difference table, the first row that is all zero ends it
+    let mut row: Vec<BigInt> = samples.iter().map(|&sample| sample.into()).collect();
+    let mut leading = vec![];
+    while !row.iter().all(Zero::is_zero) {
+        leading.push(row[0].clone());
+        row = row.windows(2).map(|window| &window[1] - &window[0]).collect();
+    }
+
+    // Newton's forward formula: p(x) = sum of leading[k] * x (x - 1) ... (x - k + 1) / k!
+    let mut coefficients = vec![BigRational::zero(); leading.len().max(1)];
+    let mut basis = vec![BigRational::one()];
+    for (k, difference) in leading.iter().enumerate() {
+        for (coefficient, b) in coefficients.iter_mut().zip(&basis) {
+            *coefficient += b * BigRational::from_integer(difference.clone());
+        }
+        // basis *= (x - k) / (k + 1)
+        let shift = BigRational::from_integer(BigInt::from(k));
+        let scale = BigRational::from_integer(BigInt::from(k + 1));
+        let mut next = vec![BigRational::zero(); basis.len() + 1];
+        for (power, b) in basis.iter().enumerate() {
+            next[power + 1] += b / &scale;
+            next[power] -= b * &shift / &scale;
+        }
+        basis = next;
+    }
+
+    let polynomial = Polynomial { coefficients };
+    Fit {
+        degree: polynomial.degree(),
+        polynomial,
+        samples: samples.len(),
+    }
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rational(numerator: i64, denominator: i64) -> BigRational {
+        BigRational::new(numerator.into(), denominator.into())
+    }
+
+    #[test]
+    fn triangular_numbers() {
+        let fit = fit(&[0, 1, 3, 6, 10, 15]);
+        assert_eq!(fit.degree, 2);
+        assert!(!fit.underdetermined());
+        assert_eq!(fit.polynomial.coefficients(), [rational(0, 1), rational(1, 2), rational(1, 2)]);
+        assert_eq!(fit.polynomial.value(6), 21.into());
+        assert_eq!(fit.polynomial.value(-1), 0.into());
+        assert_eq!(fit.polynomial.value(-3), 3.into());
+        assert_eq!(fit.polynomial.value(1_000_000), 500_000_500_000i64.into());
+        assert_eq!(fit.polynomial.at(&rational(1, 2)), rational(3, 8));
+    }
+
+    #[test]
+    fn constants() {
+        let zero = fit(&[0, 0, 0]);
+        assert_eq!(zero.degree, 0);
+        assert_eq!(zero.polynomial.value(100), 0.into());
+
+        let constant = fit(&[7, 7]);
+        assert_eq!(constant.degree, 0);
+        assert!(!constant.underdetermined());
+        assert_eq!(constant.polynomial.value(-5), 7.into());
+
+        assert!(fit::<i32>(&[]).underdetermined());
+        assert!(fit(&[7]).underdetermined());
+    }
+
+    #[test]
+    fn too_few_samples() {
+        // Three samples of a cubic look like a quadratic
+        let fit = fit(&[1, 8, 27]);
+        assert_eq!(fit.degree, 2);
+        assert!(fit.underdetermined());
+        assert_ne!(fit.polynomial.value(3), 64.into());
+    }
+
+    #[test]
+    fn large_values() {
+        let samples: Vec<i64> = (0..6).map(|x| 3_000_000_000 * x * x * x - 7 * x + 1).collect();
+        let fit = fit(&samples);
+        assert_eq!(fit.degree, 3);
+        let x = BigInt::from(1_000_000);
+        assert_eq!(fit.polynomial.value(1_000_000), BigInt::from(3_000_000_000i64) * &x * &x * &x - 7 * &x + 1);
+    }
+}